@@ -0,0 +1,377 @@
+// Movement and collision benchmarks. Run them with `cargo bench`.
+//
+// `to_cartesian` collects the cells into a new Vec; `cells` and the edges
+// read the precomputed figure tables without allocating. The "baseline"
+// benchmarks run a copy of the figure code from before the tables (see the
+// `baseline` module) on the same figures and board.
+
+#![allow(clippy::needless_return)]
+
+use std::hint::black_box;
+use std::time::Instant;
+use tetris_core::{
+    reachable_placements, Action, ActiveFigure, Board, FigureType, Game, Point, SeededRandomizer,
+    Size,
+};
+
+const FIGURES: [FigureType; 7] = [
+    FigureType::I,
+    FigureType::J,
+    FigureType::L,
+    FigureType::O,
+    FigureType::S,
+    FigureType::T,
+    FigureType::Z,
+];
+
+fn bench<F: FnMut() -> usize>(name: &str, iterations: u32, mut run: F) {
+    let mut total = 0;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        total += black_box(run());
+    }
+    let nanoseconds = start.elapsed().as_nanos() as f64 / f64::from(iterations);
+    black_box(total);
+    println!("{:<36} {:>12.1} ns/iter", name, nanoseconds);
+}
+
+fn figures() -> Vec<ActiveFigure> {
+    return FIGURES
+        .iter()
+        .flat_map(|figure_type| {
+            let figure = ActiveFigure::new(figure_type.clone(), Point { x: 3, y: 4 });
+            return vec![
+                figure.clone(),
+                figure.rotated(),
+                figure.rotated().rotated(),
+                figure.rotated().rotated().rotated(),
+            ];
+        })
+        .collect();
+}
+
+fn stacked_board() -> Board {
+    let mut board = Board::new(&Size {
+        height: 20,
+        width: 10,
+    });
+    for y in 12..20 {
+        for x in 0..10 {
+            if (x + y) % 4 != 0 {
+                board = board.replacing_figure_at_xy(x, y, Some(FigureType::Garbage));
+            }
+        }
+    }
+    return board;
+}
+
+fn main() {
+    let figures = figures();
+    let baseline_figures = baseline::figures();
+    let board = stacked_board();
+
+    bench("figure creation", 100_000, || {
+        return FIGURES
+            .iter()
+            .map(|figure_type| {
+                let figure = ActiveFigure::new(figure_type.clone(), Point { x: 3, y: 4 });
+                return figure.position().x as usize;
+            })
+            .sum();
+    });
+    bench("figure creation (baseline)", 100_000, || {
+        return FIGURES
+            .iter()
+            .map(|figure_type| {
+                let figure = baseline::ActiveFigure::new(figure_type, Point { x: 3, y: 4 });
+                return figure.position.x as usize;
+            })
+            .sum();
+    });
+
+    bench("to_cartesian (allocates)", 100_000, || {
+        return figures
+            .iter()
+            .map(|figure| figure.to_cartesian().len())
+            .sum();
+    });
+    bench("cells", 100_000, || {
+        return figures.iter().map(|figure| figure.cells().count()).sum();
+    });
+    bench("edges", 100_000, || {
+        return figures
+            .iter()
+            .map(|figure| {
+                let width = figure.right_edge() - figure.left_edge();
+                let height = figure.bottom_edge() - figure.top_edge();
+                return (width + height) as usize;
+            })
+            .sum();
+    });
+    bench("edges (baseline)", 100_000, || {
+        return baseline_figures
+            .iter()
+            .map(|figure| {
+                let width = figure.right_edge() - figure.left_edge();
+                let height = figure.bottom_edge() - figure.top_edge();
+                return (width + height) as usize;
+            })
+            .sum();
+    });
+    bench("collision with cells", 100_000, || {
+        return figures
+            .iter()
+            .filter(|figure| figure.cells().any(|cell| board.contains(cell)))
+            .count();
+    });
+    bench("collision (baseline)", 100_000, || {
+        return baseline_figures
+            .iter()
+            .filter(|figure| baseline::will_colide_with_block(figure, &board))
+            .count();
+    });
+    bench("wall kicked rotations", 100_000, || {
+        return figures
+            .iter()
+            .map(|figure| figure.wall_kicked_rotation_tests().count())
+            .sum();
+    });
+    bench("wall kicked rotations (baseline)", 100_000, || {
+        return baseline_figures
+            .iter()
+            .map(|figure| figure.wall_kicked_rotation_tests().len())
+            .sum();
+    });
+    bench("reachable placements", 1_000, || {
+        return figures
+            .iter()
+            .map(|figure| reachable_placements(&board, figure).len())
+            .sum();
+    });
+    bench("game moves and rotations", 1_000, || {
+        let size = Size {
+            height: 20,
+            width: 10,
+        };
+        let mut game = Game::new(&size, Box::new(SeededRandomizer::new(1)));
+        let actions = [
+            Action::MoveLeft,
+            Action::Rotate,
+            Action::MoveRight,
+            Action::MoveDown,
+        ];
+        for action in actions.iter().cycle().take(400) {
+            game.perform(*action);
+        }
+        return game.get_pieces() as usize;
+    });
+}
+
+// The figure code before the precomputed tables: every rotation builds a new
+// matrix, and every edge and collision check collects the blocks into a Vec.
+mod baseline {
+    use std::rc::Rc;
+    use tetris_core::{Board, FigureType, PieceDefinition, Point};
+
+    #[derive(Clone)]
+    struct Figure {
+        definition: Rc<PieceDefinition>,
+        matrix: Vec<Vec<u8>>,
+        rotation: usize,
+    }
+
+    impl Figure {
+        fn new(figure_type: &FigureType) -> Figure {
+            // The built-in definitions were built on every call.
+            let definition = Rc::new((*figure_type.definition()).clone());
+            let matrix = definition.shape.clone();
+            return Figure {
+                definition,
+                matrix,
+                rotation: 0,
+            };
+        }
+
+        fn wall_kick_tests(&self) -> Vec<Vec<Point>> {
+            return self.definition.wall_kicks.clone();
+        }
+
+        fn rotated(&self) -> Figure {
+            let shapes = &self.definition.rotation_shapes;
+            let steps = if shapes.is_empty() { 4 } else { shapes.len() };
+            let rotation = (self.rotation + 1) % steps;
+            let matrix = match shapes.get(rotation) {
+                Some(shape) => shape.clone(),
+                None => rotated_matrix(&self.matrix),
+            };
+            return Figure {
+                matrix,
+                definition: self.definition.clone(),
+                rotation,
+            };
+        }
+
+        fn to_cartesian(&self) -> Vec<Point> {
+            let mut points = vec![];
+            for (y, row) in self.matrix.iter().enumerate() {
+                for (x, element) in row.iter().enumerate() {
+                    if *element == 1 {
+                        points.push(Point {
+                            x: x as i32,
+                            y: y as i32,
+                        });
+                    }
+                }
+            }
+            return points;
+        }
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    fn rotated_matrix(matrix: &[Vec<u8>]) -> Vec<Vec<u8>> {
+        let mut data = vec![];
+        let matrix_length = matrix.len();
+        for i in 0..matrix_length {
+            let mut row = vec![];
+            for j in 0..matrix_length {
+                row.push(matrix[(matrix_length - 1) - j][i]);
+            }
+            data.push(row);
+        }
+        return data;
+    }
+
+    #[derive(Clone)]
+    pub struct ActiveFigure {
+        figure: Figure,
+        pub position: Point,
+        rotation_step: usize,
+        scale: i32,
+    }
+
+    impl ActiveFigure {
+        pub fn new(figure_type: &FigureType, position: Point) -> ActiveFigure {
+            return ActiveFigure {
+                figure: Figure::new(figure_type),
+                position,
+                rotation_step: 0,
+                scale: 1,
+            };
+        }
+
+        fn to_cartesian(&self) -> Vec<Point> {
+            let figure_points = self.figure.to_cartesian();
+            let (dx, dy) = (self.position.x, self.position.y);
+            let scale = self.scale;
+            return figure_points
+                .iter()
+                .flat_map(|point| {
+                    (0..scale * scale).map(move |cell| Point {
+                        x: point.x * scale + cell % scale + dx,
+                        y: point.y * scale + cell / scale + dy,
+                    })
+                })
+                .collect();
+        }
+
+        pub fn left_edge(&self) -> i32 {
+            return self
+                .to_cartesian()
+                .iter()
+                .map(|point| point.x)
+                .fold(i32::MAX, i32::min);
+        }
+
+        pub fn right_edge(&self) -> i32 {
+            return self
+                .to_cartesian()
+                .iter()
+                .map(|point| point.x)
+                .fold(i32::MIN, i32::max);
+        }
+
+        pub fn top_edge(&self) -> i32 {
+            return self
+                .to_cartesian()
+                .iter()
+                .map(|point| point.y)
+                .fold(i32::MAX, i32::min);
+        }
+
+        pub fn bottom_edge(&self) -> i32 {
+            return self
+                .to_cartesian()
+                .iter()
+                .map(|point| point.y)
+                .fold(i32::MIN, i32::max);
+        }
+
+        fn rotated(&self) -> ActiveFigure {
+            return ActiveFigure {
+                figure: self.figure.rotated(),
+                rotation_step: self.next_rotation_step(),
+                ..*self
+            };
+        }
+
+        pub fn wall_kicked_rotation_tests(&self) -> Vec<ActiveFigure> {
+            let scale = self.scale;
+            return self
+                .wall_kick_tests()
+                .iter()
+                .map(|point| {
+                    self.updating_position_by_xy(point.x * scale, point.y * scale)
+                        .rotated()
+                })
+                .collect();
+        }
+
+        fn wall_kick_tests(&self) -> Vec<Point> {
+            return self.figure.wall_kick_tests()[self.rotation_step].clone();
+        }
+
+        fn next_rotation_step(&self) -> usize {
+            let next_step = self.rotation_step + 1;
+            if next_step >= self.figure.definition.rotation_steps() {
+                return 0;
+            }
+            return next_step;
+        }
+
+        fn updating_position_by_xy(&self, x: i32, y: i32) -> ActiveFigure {
+            return ActiveFigure {
+                figure: self.figure.clone(),
+                position: Point {
+                    x: self.position.x + x,
+                    y: self.position.y + y,
+                },
+                ..*self
+            };
+        }
+    }
+
+    pub fn will_colide_with_block(figure: &ActiveFigure, board: &Board) -> bool {
+        let points = figure.to_cartesian();
+        for point in points {
+            if board.contains(point) {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    pub fn figures() -> Vec<ActiveFigure> {
+        return super::FIGURES
+            .iter()
+            .flat_map(|figure_type| {
+                let figure = ActiveFigure::new(figure_type, Point { x: 3, y: 4 });
+                return vec![
+                    figure.clone(),
+                    figure.rotated(),
+                    figure.rotated().rotated(),
+                    figure.rotated().rotated().rotated(),
+                ];
+            })
+            .collect();
+    }
+}
@@ -4,7 +4,7 @@ use super::{Color, Figure, FigureType, Point};
 pub struct ActiveFigure {
     figure: Figure,
     position: Point,
-    rotation_step: usize,
+    scale: i32,
 }
 
 impl ActiveFigure {
@@ -12,21 +12,46 @@ impl ActiveFigure {
         return ActiveFigure {
             figure: Figure::new(figure_type),
             position,
-            rotation_step: 0,
+            scale: 1,
+        };
+    }
+
+    // Every figure block covers a `scale` x `scale` area of the board (Big mode).
+    pub fn scaled(&self, scale: i32) -> ActiveFigure {
+        return ActiveFigure {
+            figure: self.figure.clone(),
+            scale: scale.max(1),
+            ..*self
         };
     }
 
     pub fn to_cartesian(&self) -> Vec<Point> {
-        let figure_points = self.figure.to_cartesian();
-        let (dx, dy) = (self.position.x, self.position.y);
+        return self.cells().collect();
+    }
 
-        return figure_points
+    // Board cells taken by the figure, without allocating.
+    pub fn cells(&self) -> impl Iterator<Item = Point> + '_ {
+        let (dx, dy) = (self.position.x, self.position.y);
+        let scale = self.scale;
+        return self
+            .figure
+            .blocks()
+            .as_slice()
             .iter()
-            .map(|point| Point {
-                x: point.x + dx,
-                y: point.y + dy,
-            })
-            .collect();
+            .flat_map(move |point| {
+                (0..scale * scale).map(move |cell| Point {
+                    x: point.x * scale + cell % scale + dx,
+                    y: point.y * scale + cell / scale + dy,
+                })
+            });
+    }
+
+    pub fn spawn_offset(&self) -> Point {
+        return self.figure.spawn_offset();
+    }
+
+    pub fn scale(&self) -> i32 {
+        return self.scale;
     }
 
     pub fn color(&self) -> Color {
@@ -37,38 +62,28 @@ impl ActiveFigure {
         return self.position;
     }
 
+    pub fn rotation_step(&self) -> usize {
+        return self.figure.rotation_step();
+    }
+
     pub fn get_type(&self) -> FigureType {
         return self.figure.get_type();
     }
 
     pub fn left_edge(&self) -> i32 {
-        let points = self.to_cartesian();
-        return points.iter().fold(i32::max_value(), |edge, point| {
-            if point.x < edge {
-                return point.x;
-            }
-            return edge;
-        });
+        return self.figure.blocks().min().x * self.scale + self.position.x;
     }
 
     pub fn right_edge(&self) -> i32 {
-        let points = self.to_cartesian();
-        return points.iter().fold(i32::min_value(), |edge, point| {
-            if point.x > edge {
-                return point.x;
-            }
-            return edge;
-        });
+        return (self.figure.blocks().max().x + 1) * self.scale - 1 + self.position.x;
+    }
+
+    pub fn top_edge(&self) -> i32 {
+        return self.figure.blocks().min().y * self.scale + self.position.y;
     }
 
     pub fn bottom_edge(&self) -> i32 {
-        let points = self.to_cartesian();
-        return points.iter().fold(i32::min_value(), |edge, point| {
-            if point.y > edge {
-                return point.y;
-            }
-            return edge;
-        });
+        return (self.figure.blocks().max().y + 1) * self.scale - 1 + self.position.y;
     }
 
     pub fn rotated(&self) -> ActiveFigure {
@@ -76,7 +91,7 @@ impl ActiveFigure {
         return ActiveFigure {
             figure,
             position: self.position,
-            rotation_step: self.next_rotation_step(),
+            scale: self.scale,
         };
     }
 
@@ -84,38 +99,39 @@ impl ActiveFigure {
         return self.updating_position_by_xy(0, 1);
     }
 
-    pub fn moved_left(&self) -> ActiveFigure {
-        return self.updating_position_by_xy(-1, 0);
+    pub fn moved_up(&self) -> ActiveFigure {
+        return self.updating_position_by_xy(0, -1);
     }
 
-    pub fn moved_right(&self) -> ActiveFigure {
-        return self.updating_position_by_xy(1, 0);
+    pub fn moved_left(&self) -> ActiveFigure {
+        return self.updating_position_by_xy(-self.scale, 0);
     }
 
-    pub fn wall_kicked_rotation_tests(&self) -> Vec<ActiveFigure> {
-        return self
-            .wall_kick_tests()
-            .iter()
-            .map(|point| self.updating_position_by_xy(point.x, point.y).rotated())
-            .collect();
+    pub fn moved_right(&self) -> ActiveFigure {
+        return self.updating_position_by_xy(self.scale, 0);
     }
 
-    fn wall_kick_tests(&self) -> Vec<Point> {
-        let kick_wall_tests_matrix = self.figure.wall_kick_tests();
-        return kick_wall_tests_matrix[self.rotation_step].clone();
+    // Keeps the position within the board columns on a wrapping board.
+    pub fn wrapped_around(&self, width: i32) -> ActiveFigure {
+        if width <= 0 {
+            return self.clone();
+        }
+        let x = self.position.x.rem_euclid(width);
+        return self.updating_position_by_xy(x - self.position.x, 0);
     }
 
-    fn next_rotation_step(&self) -> usize {
-        match self.get_type() {
-            FigureType::O => 0,
-            _ => {
-                let next_step = self.rotation_step + 1;
-                if next_step > 3 {
-                    return 0;
-                }
-                return next_step;
-            }
-        }
+    // Rotated candidates in wall kick test order, built lazily so callers
+    // stopping at the first valid one don't build the rest.
+    pub fn wall_kicked_rotation_tests(&self) -> impl Iterator<Item = ActiveFigure> + '_ {
+        let scale = self.scale;
+        return self
+            .figure
+            .wall_kick_tests(self.rotation_step())
+            .iter()
+            .map(move |point| {
+                self.updating_position_by_xy(point.x * scale, point.y * scale)
+                    .rotated()
+            });
     }
 
     fn updating_position_by_xy(&self, x: i32, y: i32) -> ActiveFigure {
@@ -146,6 +162,20 @@ mod active_figure_tests {
         assert_eq!(coordinates, expectation);
     }
     #[test]
+    fn test_cells_match_edges() {
+        let figure = ActiveFigure::new(FigureType::J, Point { x: 3, y: 1 })
+            .rotated()
+            .scaled(2);
+        let cells: Vec<Point> = figure.cells().collect();
+        assert_eq!(cells, figure.to_cartesian());
+        let xs: Vec<i32> = cells.iter().map(|cell| cell.x).collect();
+        let ys: Vec<i32> = cells.iter().map(|cell| cell.y).collect();
+        assert_eq!(xs.iter().min(), Some(&figure.left_edge()));
+        assert_eq!(xs.iter().max(), Some(&figure.right_edge()));
+        assert_eq!(ys.iter().min(), Some(&figure.top_edge()));
+        assert_eq!(ys.iter().max(), Some(&figure.bottom_edge()));
+    }
+    #[test]
     fn test_color() {
         let figure_type = FigureType::T;
         let figure = ActiveFigure::new(FigureType::T, Point { x: 0, y: 0 });
@@ -192,8 +222,8 @@ mod active_figure_tests {
         let figure = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
         let rotated_01 = figure.rotated();
         let rotated_02 = rotated_01.rotated();
-        assert_eq!(rotated_01.rotation_step, 0);
-        assert_eq!(rotated_02.rotation_step, 0);
+        assert_eq!(rotated_01.rotation_step(), 0);
+        assert_eq!(rotated_02.rotation_step(), 0);
     }
     #[test]
     fn test_totation_steps_non_o_figures() {
@@ -203,11 +233,11 @@ mod active_figure_tests {
         let rotation_03 = rotation_02.rotated();
         let rotation_04 = rotation_03.rotated();
 
-        assert_eq!(figure.rotation_step, 0);
-        assert_eq!(rotation_01.rotation_step, 1);
-        assert_eq!(rotation_02.rotation_step, 2);
-        assert_eq!(rotation_03.rotation_step, 3);
-        assert_eq!(rotation_04.rotation_step, 0);
+        assert_eq!(figure.rotation_step(), 0);
+        assert_eq!(rotation_01.rotation_step(), 1);
+        assert_eq!(rotation_02.rotation_step(), 2);
+        assert_eq!(rotation_03.rotation_step(), 3);
+        assert_eq!(rotation_04.rotation_step(), 0);
     }
     #[test]
     fn test_moved_left() {
@@ -224,4 +254,30 @@ mod active_figure_tests {
         let figure = ActiveFigure::new(FigureType::I, Point { x: 1, y: 0 });
         assert_eq!(figure.moved_down().position(), Point { x: 1, y: 1 });
     }
+    #[test]
+    fn test_to_cartesian_scaled() {
+        let figure = ActiveFigure::new(FigureType::T, Point { x: 2, y: 1 }).scaled(2);
+        let coordinates = figure.to_cartesian();
+        assert_eq!(coordinates.len(), 16);
+        assert_eq!(coordinates[0], Point { x: 4, y: 1 });
+        assert_eq!(coordinates[3], Point { x: 5, y: 2 });
+        assert_eq!(figure.left_edge(), 2);
+        assert_eq!(figure.right_edge(), 7);
+        assert_eq!(figure.bottom_edge(), 4);
+    }
+    #[test]
+    fn test_scaled_moves_two_columns() {
+        let figure = ActiveFigure::new(FigureType::I, Point { x: 2, y: 0 }).scaled(2);
+        assert_eq!(figure.moved_left().position(), Point { x: 0, y: 0 });
+        assert_eq!(figure.moved_right().position(), Point { x: 4, y: 0 });
+        assert_eq!(figure.moved_down().position(), Point { x: 2, y: 1 });
+    }
+    #[test]
+    fn test_scaled_wall_kicks() {
+        let figure = ActiveFigure::new(FigureType::L, Point { x: 4, y: 4 }).scaled(2);
+        let tests: Vec<ActiveFigure> = figure.wall_kicked_rotation_tests().collect();
+        assert_eq!(tests[1].position(), Point { x: 2, y: 4 });
+        assert_eq!(tests[2].position(), Point { x: 2, y: 6 });
+        assert_eq!(tests[2].scale(), 2);
+    }
 }
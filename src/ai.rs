@@ -0,0 +1,261 @@
+use super::placement::reachable_placements;
+use super::{Action, ActiveFigure, Board, Game, Placement};
+
+// Weight of every feature in the evaluation. Positive weights reward the
+// feature, negative ones penalize it.
+// - landing_height: height of the center of the placed figure.
+// - eroded_cells: completed lines times the figure cells in them.
+// - row_transitions, column_transitions: changes between filled and empty
+//   cells along rows and columns, walls and floor count as filled.
+// - wells: sum of the well depths, counting 1 + 2 + ... for every well cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    pub aggregate_height: f64,
+    pub holes: f64,
+    pub bumpiness: f64,
+    pub wells: f64,
+    pub completed_lines: f64,
+    pub landing_height: f64,
+    pub eroded_cells: f64,
+    pub row_transitions: f64,
+    pub column_transitions: f64,
+}
+
+impl Weights {
+    // Pierre Dellacherie's hand tuned weights.
+    pub fn dellacherie() -> Weights {
+        return Weights {
+            aggregate_height: 0.0,
+            holes: -4.0,
+            bumpiness: 0.0,
+            wells: -1.0,
+            completed_lines: 0.0,
+            landing_height: -1.0,
+            eroded_cells: 1.0,
+            row_transitions: -1.0,
+            column_transitions: -1.0,
+        };
+    }
+
+    // The El-Tetris weights, tuned with particle swarm optimization.
+    pub fn el_tetris() -> Weights {
+        return Weights {
+            aggregate_height: 0.0,
+            holes: -7.899265427351652,
+            bumpiness: 0.0,
+            wells: -3.3855972247263626,
+            completed_lines: 0.0,
+            landing_height: -4.500158825082766,
+            eroded_cells: 3.4181268101392694,
+            row_transitions: -3.2178882868487753,
+            column_transitions: -9.348695305445199,
+        };
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        return Weights::el_tetris();
+    }
+}
+
+// Heuristic player. It tries every reachable placement of the active figure
+// (and of the next one, looking ahead) and plays the best rated one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ai {
+    weights: Weights,
+    lookahead: bool,
+}
+
+impl Ai {
+    pub fn new(weights: Weights) -> Ai {
+        return Ai {
+            weights,
+            lookahead: true,
+        };
+    }
+
+    pub fn looking_ahead(&self, lookahead: bool) -> Ai {
+        return Ai {
+            weights: self.weights,
+            lookahead,
+        };
+    }
+
+    pub fn get_weights(&self) -> &Weights {
+        return &self.weights;
+    }
+
+    // Rating of the board after locking the figure at the placement.
+    pub fn evaluate(&self, board: &Board, placement: &Placement) -> f64 {
+        return match self.placing(board, placement) {
+            Some((placed, score)) => score + self.board_score(&placed),
+            None => f64::NEG_INFINITY,
+        };
+    }
+
+    pub fn best_placement(&self, game: &Game) -> Option<Placement> {
+        let board = game.get_board();
+        let next = game.get_next_figure().filter(|_| self.lookahead);
+        let mut best: Option<(f64, Placement)> = None;
+        for placement in game.reachable_placements() {
+            let score = match (self.placing(board, &placement), next) {
+                (Some((placed, score)), Some(next)) => score + self.best_score(&placed, next),
+                (Some((placed, score)), None) => score + self.board_score(&placed),
+                (None, _) => f64::NEG_INFINITY,
+            };
+            let is_better = match &best {
+                Some((best_score, _)) => score > *best_score,
+                None => true,
+            };
+            if is_better {
+                best = Some((score, placement));
+            }
+        }
+        return best.map(|(_, placement)| placement);
+    }
+
+    // Actions taking the active figure to the best placement, ending with a hard drop.
+    pub fn next_actions(&self, game: &Game) -> Option<Vec<Action>> {
+        let placement = self.best_placement(game)?;
+        return game.path_to(&placement);
+    }
+
+    // Places the active figure. Returns false when there is nothing to play.
+    pub fn play(&self, game: &mut Game) -> bool {
+        if game.is_finished() {
+            return false;
+        }
+        if let Some(actions) = self.next_actions(game) {
+            for action in actions {
+                game.perform(action);
+            }
+            return true;
+        }
+        return false;
+    }
+
+    fn best_score(&self, board: &Board, figure: &ActiveFigure) -> f64 {
+        return reachable_placements(board, figure)
+            .iter()
+            .map(|placement| self.evaluate(board, placement))
+            .fold(f64::NEG_INFINITY, f64::max);
+    }
+
+    // The board with the figure locked and the completed lines removed, and
+    // the score of the features that depend on the placement itself.
+    fn placing(&self, board: &Board, placement: &Placement) -> Option<(Board, f64)> {
+        let cells = placement.cells();
+        if cells.is_empty() || cells.iter().any(|cell| cell.y < 0) {
+            return None;
+        }
+        let mut placed = placement.locked_on(board);
+        let lines: Vec<usize> = (0..placed.height())
+            .filter(|line| placed.is_line_complete(*line))
+            .collect();
+        let cleared_cells = cells
+            .iter()
+            .filter(|cell| lines.contains(&(cell.y as usize)))
+            .count();
+        let top = cells.first().map_or(0, |cell| cell.y);
+        let bottom = cells.last().map_or(0, |cell| cell.y);
+        let landing_height = board.height() as f64 - (top + bottom) as f64 / 2.0;
+        let score = self.weights.landing_height * landing_height
+            + self.weights.eroded_cells * (lines.len() * cleared_cells) as f64
+            + self.weights.completed_lines * lines.len() as f64;
+        placed.clear_rows(&lines);
+        return Some((placed, score));
+    }
+
+    fn board_score(&self, board: &Board) -> f64 {
+        let wells: usize = board
+            .well_depths()
+            .iter()
+            .map(|depth| depth * (depth + 1) / 2)
+            .sum();
+        let weights = &self.weights;
+        return weights.aggregate_height * board.column_heights().iter().sum::<usize>() as f64
+            + weights.holes * board.holes() as f64
+            + weights.bumpiness * board.bumpiness() as f64
+            + weights.wells * wells as f64
+            + weights.row_transitions * board.row_transitions() as f64
+            + weights.column_transitions * board.column_transitions() as f64;
+    }
+}
+
+#[cfg(test)]
+mod ai_tests {
+    use super::super::{FigureType, GameMode, Point, SeededRandomizer, SevenBagGenerator, Size};
+    use super::*;
+
+    fn board_from(rows: &[&str]) -> Board {
+        let rows = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|cell| match cell {
+                        'X' => Some(FigureType::I),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        return Board::from_rows(rows);
+    }
+
+    fn seeded_game(seed: u64) -> Game {
+        let size = Size {
+            height: 20,
+            width: 10,
+        };
+        return Game::with_generator(
+            &size,
+            Box::new(SeededRandomizer::new(seed)),
+            GameMode::Endless,
+            Box::new(SevenBagGenerator::new(seed)),
+        );
+    }
+
+    #[test]
+    fn test_picks_the_line_clear() {
+        let board = board_from(&["....", "....", "....", "....", "XXX.", "XXX."]);
+        let figure = ActiveFigure::new(FigureType::I, Point { x: 0, y: 0 });
+        let ai = Ai::new(Weights::el_tetris());
+        let placements = reachable_placements(&board, &figure);
+        let best = placements
+            .iter()
+            .max_by(|a, b| {
+                let (a, b) = (ai.evaluate(&board, a), ai.evaluate(&board, b));
+                return a.partial_cmp(&b).unwrap();
+            })
+            .unwrap();
+        assert!(best.cells().contains(&Point { x: 3, y: 5 }));
+    }
+    #[test]
+    fn test_plays_without_topping_out() {
+        let mut game = seeded_game(7);
+        let ai = Ai::new(Weights::el_tetris()).looking_ahead(false);
+        for _ in 0..100 {
+            assert!(ai.play(&mut game));
+        }
+        assert!(!game.is_game_over());
+        assert_eq!(game.get_pieces(), 100);
+        assert!(game.get_lines() >= 30);
+    }
+    #[test]
+    fn test_plays_looking_ahead() {
+        let mut game = seeded_game(3);
+        let ai = Ai::new(Weights::dellacherie());
+        for _ in 0..10 {
+            assert!(ai.play(&mut game));
+        }
+        assert!(!game.is_game_over());
+        assert_eq!(game.get_pieces(), 10);
+    }
+    #[test]
+    fn test_next_actions_end_with_hard_drop() {
+        let game = seeded_game(1);
+        let actions = Ai::new(Weights::default()).next_actions(&game).unwrap();
+        assert_eq!(actions.last(), Some(&Action::HardDrop));
+    }
+}
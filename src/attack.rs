@@ -0,0 +1,138 @@
+use super::LineClear;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttackTable {
+    pub single: u32,
+    pub double: u32,
+    pub triple: u32,
+    pub tetris: u32,
+    pub t_spin_single: u32,
+    pub t_spin_double: u32,
+    pub t_spin_triple: u32,
+    pub back_to_back_bonus: u32,
+    pub perfect_clear: u32,
+    // Extra lines indexed by combo count, the last value is used for longer combos.
+    pub combo: Vec<u32>,
+}
+
+impl AttackTable {
+    pub fn attack_for(&self, clear: &LineClear) -> u32 {
+        if clear.lines == 0 {
+            return 0;
+        }
+        let mut attack = self.base_attack(clear);
+        if clear.back_to_back {
+            attack += self.back_to_back_bonus;
+        }
+        attack += self.combo_bonus(clear.combo);
+        if clear.perfect_clear {
+            attack += self.perfect_clear;
+        }
+        return attack;
+    }
+
+    fn base_attack(&self, clear: &LineClear) -> u32 {
+        return match (clear.t_spin, clear.lines) {
+            (true, 1) => self.t_spin_single,
+            (true, 2) => self.t_spin_double,
+            (true, _) => self.t_spin_triple,
+            (false, 1) => self.single,
+            (false, 2) => self.double,
+            (false, 3) => self.triple,
+            (false, _) => self.tetris,
+        };
+    }
+
+    fn combo_bonus(&self, combo: u32) -> u32 {
+        let index = (combo as usize).min(self.combo.len().saturating_sub(1));
+        return self.combo.get(index).cloned().unwrap_or(0);
+    }
+}
+
+impl Default for AttackTable {
+    fn default() -> AttackTable {
+        return AttackTable {
+            single: 0,
+            double: 1,
+            triple: 2,
+            tetris: 4,
+            t_spin_single: 2,
+            t_spin_double: 4,
+            t_spin_triple: 6,
+            back_to_back_bonus: 1,
+            perfect_clear: 10,
+            combo: vec![0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5],
+        };
+    }
+}
+
+#[cfg(test)]
+mod attack_tests {
+    use super::*;
+
+    fn clear(lines: u32) -> LineClear {
+        return LineClear {
+            lines,
+            t_spin: false,
+            combo: 0,
+            back_to_back: false,
+            perfect_clear: false,
+            chain: 1,
+        };
+    }
+
+    #[test]
+    fn test_no_lines_no_attack() {
+        let table = AttackTable::default();
+        assert_eq!(table.attack_for(&clear(0)), 0);
+    }
+    #[test]
+    fn test_line_attacks() {
+        let table = AttackTable::default();
+        assert_eq!(table.attack_for(&clear(1)), 0);
+        assert_eq!(table.attack_for(&clear(2)), 1);
+        assert_eq!(table.attack_for(&clear(3)), 2);
+        assert_eq!(table.attack_for(&clear(4)), 4);
+    }
+    #[test]
+    fn test_t_spin_attacks() {
+        let table = AttackTable::default();
+        let t_spin_double = LineClear {
+            t_spin: true,
+            ..clear(2)
+        };
+        assert_eq!(table.attack_for(&t_spin_double), 4);
+    }
+    #[test]
+    fn test_back_to_back_bonus() {
+        let table = AttackTable::default();
+        let tetris = LineClear {
+            back_to_back: true,
+            ..clear(4)
+        };
+        assert_eq!(table.attack_for(&tetris), 5);
+    }
+    #[test]
+    fn test_combo_bonus() {
+        let table = AttackTable::default();
+        let combo = LineClear {
+            combo: 3,
+            ..clear(1)
+        };
+        let long_combo = LineClear {
+            combo: 30,
+            ..clear(1)
+        };
+        assert_eq!(table.attack_for(&combo), 1);
+        assert_eq!(table.attack_for(&long_combo), 5);
+    }
+    #[test]
+    fn test_perfect_clear_bonus() {
+        let table = AttackTable::default();
+        let perfect_clear = LineClear {
+            perfect_clear: true,
+            ..clear(2)
+        };
+        assert_eq!(table.attack_for(&perfect_clear), 11);
+    }
+}
@@ -1,44 +1,136 @@
-use super::{FigureType, Matrix, Point, Size};
-use std::collections::VecDeque;
+use super::{FigureType, LineClearGravity, Point, Size};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
 
-#[derive(Debug)]
+// Columns stored in every occupancy word.
+const WORD_BITS: usize = 64;
+
+// Palette entries always present: empty cells and garbage.
+const EMPTY_COLOR: u8 = 0;
+const GARBAGE_COLOR: u8 = 1;
+
+// A cell was given a figure type when the palette already holds the 255
+// different types it can index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TooManyFigureTypes;
+
+impl fmt::Display for TooManyFigureTypes {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        return write!(formatter, "a board can't hold more than 255 figure types");
+    }
+}
+
+impl Error for TooManyFigureTypes {}
+
+// Obstacles are permanent blocks drawn on the board, masked cells are not
+// part of the playfield. Both stay in place when lines are removed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CellKind {
+    Playable,
+    Obstacle,
+    Masked,
+}
+
+// Bitboard: every row is stored as integer words with bit x % 64 of word
+// x / 64 set when column x is filled, so collisions and complete lines are
+// checked without walking the cells. Boards up to 64 columns wide use one
+// word per row. Obstacles and masked cells have their own bitboards. The figure type of
+// every cell is an index into a small palette of the types on the board.
+#[derive(Debug, Clone)]
 pub struct Board {
-    matrix: Matrix<Option<FigureType>>,
+    width: usize,
+    height: usize,
+    words: usize,
+    filled: Vec<u64>,
+    obstacles: Vec<u64>,
+    masked: Vec<u64>,
+    colors: Vec<u8>,
+    palette: Vec<Option<FigureType>>,
+    garbage_lines: Vec<bool>,
+    lock_times: Vec<f64>,
+    wraps: bool,
 }
 
 impl Board {
     pub fn new(size: &Size) -> Board {
-        let mut cells = vec![];
-        for _y in 0..size.height {
-            let line = Board::get_empty_line(size.width);
-            cells.push(line);
+        let words = size.width.div_ceil(WORD_BITS);
+        return Board {
+            width: size.width,
+            height: size.height,
+            words,
+            filled: vec![0; words * size.height],
+            obstacles: vec![0; words * size.height],
+            masked: vec![0; words * size.height],
+            colors: vec![0; size.width * size.height],
+            palette: vec![None, Some(FigureType::Garbage)],
+            garbage_lines: vec![false; size.height],
+            lock_times: vec![0.0; size.width * size.height],
+            wraps: false,
+        };
+    }
+
+    // An empty board with the shape given by the cell kinds.
+    pub fn with_cell_kinds(cell_kinds: Vec<Vec<CellKind>>) -> Board {
+        let height = cell_kinds.len();
+        let width = cell_kinds.first().map_or(0, |row| row.len());
+        let mut board = Board::new(&Size { height, width });
+        for (y, row) in cell_kinds.into_iter().enumerate() {
+            for (x, cell_kind) in row.into_iter().enumerate() {
+                board.set_cell_kind(x, y, cell_kind);
+            }
         }
-        let matrix = Matrix::new(cells);
-        return Board { matrix };
+        return board;
+    }
+
+    pub fn from_rows(rows: Vec<Vec<Option<FigureType>>>) -> Board {
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+        let mut board = Board::new(&Size { height, width });
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, cell) in row.into_iter().enumerate() {
+                // Cells of types beyond the 256th are left empty.
+                let _ = board.set(x, y, cell);
+            }
+        }
+        return board;
+    }
+
+    // On a wrapping board moving past the right edge re-enters from the left.
+    pub fn wrapping_around(&self, wraps: bool) -> Board {
+        return Board {
+            wraps,
+            ..self.clone()
+        };
+    }
+
+    pub fn is_wrapping_around(&self) -> bool {
+        return self.wraps;
     }
 
-    fn get_empty_line(width: usize) -> Vec<Option<FigureType>> {
-        let mut line: Vec<Option<FigureType>> = vec![];
-        for _x in 0..width {
-            line.push(None);
+    pub fn wrapped_point(&self, point: Point) -> Point {
+        if !self.wraps || self.width() == 0 {
+            return point;
         }
-        return line;
+        return Point {
+            x: point.x.rem_euclid(self.width() as i32),
+            y: point.y,
+        };
     }
 
     pub fn height(&self) -> usize {
-        return self.matrix.height();
+        return self.height;
     }
 
     pub fn width(&self) -> usize {
-        return self.matrix.width();
+        return self.width;
     }
 
     pub fn figure_at_xy(&self, x: usize, y: usize) -> &Option<FigureType> {
-        if let Some(element) = self.matrix.at_xy(x, y) {
-            return element;
-        } else {
+        if !self.is_inside(x, y) {
             return &None;
         }
+        return &self.palette[self.colors[self.index(x, y)] as usize];
     }
 
     pub fn replacing_figure_at_xy(
@@ -47,44 +139,688 @@ impl Board {
         y: usize,
         figure_type: Option<FigureType>,
     ) -> Board {
-        let matrix = self.matrix.replacing_at_xy(x, y, figure_type);
-        return Board { matrix };
+        // The cell is left unchanged when the board can't hold its type,
+        // `set` returns the error instead.
+        let mut board = self.clone();
+        let _ = board.set(x, y, figure_type);
+        return board;
+    }
+
+    // Turning a cell into an obstacle or a masked cell removes its content.
+    pub fn replacing_cell_kind_at_xy(&self, x: usize, y: usize, cell_kind: CellKind) -> Board {
+        let mut board = self.clone();
+        board.set_cell_kind(x, y, cell_kind);
+        if cell_kind != CellKind::Playable {
+            board.clear_cell(x, y);
+        }
+        return board;
+    }
+
+    pub fn cell_kind_at_xy(&self, x: usize, y: usize) -> CellKind {
+        if !self.is_inside(x, y) {
+            return CellKind::Masked;
+        }
+        let (word, bit) = self.bit(x, y);
+        if self.obstacles[word] & bit != 0 {
+            return CellKind::Obstacle;
+        }
+        if self.masked[word] & bit != 0 {
+            return CellKind::Masked;
+        }
+        return CellKind::Playable;
+    }
+
+    pub fn is_playable_at_xy(&self, x: usize, y: usize) -> bool {
+        return self.cell_kind_at_xy(x, y) == CellKind::Playable;
+    }
+
+    // Places a figure block remembering the game time it was locked at.
+    pub fn locking_figure_at_xy(
+        &self,
+        x: usize,
+        y: usize,
+        figure_type: FigureType,
+        time: f64,
+    ) -> Board {
+        let mut board = self.clone();
+        let _ = board.set_locked(x, y, Some(figure_type), time);
+        return board;
+    }
+
+    pub fn lock_time_at_xy(&self, x: usize, y: usize) -> f64 {
+        if !self.is_inside(x, y) {
+            return 0.0;
+        }
+        return self.lock_times[self.index(x, y)];
     }
 
     pub fn contains(&self, point: Point) -> bool {
+        let point = self.wrapped_point(point);
         if point.x < 0 || point.y < 0 {
             return false;
         }
+        let (x, y) = (point.x as usize, point.y as usize);
+        if !self.is_inside(x, y) {
+            return false;
+        }
+        let (word, bit) = self.bit(x, y);
+        return self.blocked_word(word) & bit != 0;
+    }
+
+    pub fn get_line(&self, line: usize) -> Option<Vec<Option<FigureType>>> {
+        if line >= self.height() {
+            return None;
+        }
+        return Some(
+            (0..self.width())
+                .map(|x| self.figure_at_xy(x, line).clone())
+                .collect(),
+        );
+    }
+
+    pub fn is_line_empty(&self, line: usize) -> bool {
+        if line >= self.height() {
+            return true;
+        }
+        return self.filled[self.row_words(line)]
+            .iter()
+            .all(|word| *word == 0);
+    }
+
+    // A line is complete when all its playable cells are filled.
+    pub fn is_line_complete(&self, line: usize) -> bool {
+        if line >= self.height() {
+            return false;
+        }
+        let mut has_playable_cells = false;
+        for (column_word, word) in self.row_words(line).enumerate() {
+            let playable =
+                self.word_mask(column_word) & !(self.obstacles[word] | self.masked[word]);
+            if self.filled[word] & playable != playable {
+                return false;
+            }
+            has_playable_cells |= playable != 0;
+        }
+        return has_playable_cells;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.filled.iter().all(|word| *word == 0);
+    }
+
+    pub fn is_garbage_line(&self, line: usize) -> bool {
+        return self.garbage_lines.get(line).cloned().unwrap_or(false);
+    }
+
+    pub fn garbage_lines_count(&self) -> usize {
         return self
-            .figure_at_xy(point.x as usize, point.y as usize)
-            .is_some();
+            .garbage_lines
+            .iter()
+            .filter(|is_garbage| **is_garbage)
+            .count();
+    }
+
+    // BOARD ANALYSIS
+    // Walls and the floor count as filled, obstacles and masked cells too.
+    // On a wrapping board the sides are the other edge of the board.
+
+    // Height of the highest filled cell of every column.
+    pub fn column_heights(&self) -> Vec<usize> {
+        let height = self.height() as i32;
+        return (0..self.width() as i32)
+            .map(|x| {
+                let top = (0..height).find(|y| self.is_filled_at(x, *y));
+                return top.map_or(0, |top| (height - top) as usize);
+            })
+            .collect();
     }
 
-    pub fn get_line(&self, line: usize) -> Option<&Vec<Option<FigureType>>> {
-        return self.matrix.row_at(line);
+    // Empty cells below the top of their column.
+    pub fn holes(&self) -> usize {
+        return self.count_below_top(|board, x, y| !board.is_filled_at(x, y));
+    }
+
+    // Filled cells with a hole somewhere below them.
+    pub fn covered_cells(&self) -> usize {
+        let height = self.height() as i32;
+        return self.count_below_top(|board, x, y| {
+            return board.is_filled_at(x, y)
+                && (y + 1..height).any(|below| !board.is_filled_at(x, below));
+        });
     }
 
-    pub fn removing_lines(&self, lines: &Vec<usize>) -> Board {
-        let mut new_board_data: VecDeque<Vec<Option<FigureType>>> = VecDeque::default();
-        for line_number in 0..self.height() {
-            if lines.contains(&line_number) {
-                new_board_data.push_front(Board::get_empty_line(self.width()));
-            } else {
-                if let Some(line) = self.get_line(line_number) {
-                    new_board_data.push_back(line.clone());
+    // Sum of the height differences between neighbouring columns.
+    pub fn bumpiness(&self) -> usize {
+        return self
+            .column_heights()
+            .windows(2)
+            .map(|pair| pair[0].abs_diff(pair[1]))
+            .sum();
+    }
+
+    // Changes between filled and empty cells along every row.
+    pub fn row_transitions(&self) -> usize {
+        let width = self.width() as i32;
+        return (0..self.height() as i32)
+            .map(|y| {
+                return (0..=width)
+                    .filter(|x| self.is_filled_at(x - 1, y) != self.is_filled_at(*x, y))
+                    .count();
+            })
+            .sum();
+    }
+
+    // Changes between filled and empty cells along every column.
+    pub fn column_transitions(&self) -> usize {
+        let height = self.height() as i32;
+        return (0..self.width() as i32)
+            .map(|x| {
+                return (1..=height)
+                    .filter(|y| self.is_filled_at(x, y - 1) != self.is_filled_at(x, *y))
+                    .count();
+            })
+            .sum();
+    }
+
+    // How far every column is below both of its neighbours, 0 if it isn't a well.
+    pub fn well_depths(&self) -> Vec<usize> {
+        let heights = self.column_heights();
+        let width = heights.len() as i32;
+        let height_at = |x: i32| {
+            if self.wraps && width > 0 {
+                return heights[x.rem_euclid(width) as usize];
+            }
+            return heights.get(x as usize).cloned().unwrap_or(self.height());
+        };
+        return (0..width)
+            .map(|x| {
+                let sides = height_at(x - 1).min(height_at(x + 1));
+                return sides.saturating_sub(height_at(x));
+            })
+            .collect();
+    }
+
+    // Centers of the slots where a T pointing down fits with at least three
+    // of its four corners filled, ready for a T-Spin.
+    pub fn t_slots(&self) -> Vec<Point> {
+        let mut slots = vec![];
+        for y in 0..self.height() as i32 {
+            for x in 0..self.width() as i32 {
+                let fits = [(x - 1, y), (x, y), (x + 1, y), (x, y + 1)]
+                    .iter()
+                    .all(|(x, y)| !self.is_filled_at(*x, *y));
+                let corners = [
+                    (x - 1, y - 1),
+                    (x + 1, y - 1),
+                    (x - 1, y + 1),
+                    (x + 1, y + 1),
+                ];
+                let filled_corners = corners
+                    .iter()
+                    .filter(|(x, y)| self.is_filled_at(*x, *y))
+                    .count();
+                let is_supported = self.is_filled_at(x, y + 2);
+                if fits && filled_corners >= 3 && is_supported {
+                    slots.push(Point { x, y });
                 }
             }
         }
-        return Board {
-            matrix: Matrix::new(Vec::from(new_board_data)),
-        };
+        return slots;
+    }
+
+    fn is_filled_at(&self, x: i32, y: i32) -> bool {
+        let is_wall = !self.wraps && (x < 0 || x >= self.width() as i32);
+        if is_wall || y >= self.height() as i32 {
+            return true;
+        }
+        return self.contains(Point { x, y });
+    }
+
+    fn count_below_top(&self, counts: impl Fn(&Board, i32, i32) -> bool) -> usize {
+        let height = self.height() as i32;
+        return self
+            .column_heights()
+            .into_iter()
+            .enumerate()
+            .map(|(x, column_height)| {
+                return (height - column_height as i32..height)
+                    .filter(|y| counts(self, x as i32, *y))
+                    .count();
+            })
+            .sum();
+    }
+
+    pub fn removing_lines(&self, lines: &[usize]) -> Board {
+        let mut board = self.clone();
+        board.clear_rows(lines);
+        return board;
+    }
+
+    pub fn removing_lines_with_gravity(&self, lines: &[usize], gravity: LineClearGravity) -> Board {
+        let mut board = self.clone();
+        board.clear_rows_with_gravity(lines, gravity);
+        return board;
+    }
+
+    // Color matching: chunks of at least `min_size` blocks of the same figure
+    // type. Garbage never matches.
+    pub fn matching_groups(&self, min_size: usize) -> Vec<Vec<(usize, usize)>> {
+        return self
+            .get_chunks(true)
+            .into_iter()
+            .filter(|chunk| chunk.len() >= min_size.max(1))
+            .filter(|chunk| {
+                let (x, y) = chunk[0];
+                return *self.figure_at_xy(x, y) != Some(FigureType::Garbage);
+            })
+            .collect();
+    }
+
+    // Removes single cells as well as lines. The remaining blocks always fall
+    // as chunks, since shifting whole rows would leave the holes floating.
+    pub fn removing_cells_with_gravity(
+        &self,
+        lines: &[usize],
+        cells: &[(usize, usize)],
+        gravity: LineClearGravity,
+    ) -> Board {
+        let mut board = self.clone();
+        board.clear_cells_with_gravity(lines, cells, gravity);
+        return board;
+    }
+
+    // Empties the lines leaving everything else in place.
+    fn empty_rows(&mut self, lines: &[usize]) {
+        let height = self.height();
+        for line in lines.iter().filter(|line| **line < height) {
+            self.empty_row(*line);
+        }
+    }
+
+    // Drops every chunk of connected blocks until it lands, bottom chunks first.
+    fn settle_chunks(&mut self, same_type_only: bool) {
+        let mut chunks = self.get_chunks(same_type_only);
+        chunks.sort_by_key(|chunk| chunk.iter().map(|(_, y)| *y).max().map(|y| -(y as i64)));
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for chunk in chunks.iter_mut() {
+                while self.can_chunk_fall(chunk) {
+                    let cells: Vec<(u8, f64)> = chunk
+                        .iter()
+                        .map(|(x, y)| {
+                            let index = self.index(*x, *y);
+                            return (self.colors[index], self.lock_times[index]);
+                        })
+                        .collect();
+                    for (x, y) in chunk.iter() {
+                        self.clear_cell(*x, *y);
+                    }
+                    for ((x, y), (color, lock_time)) in chunk.iter_mut().zip(cells) {
+                        *y += 1;
+                        self.write_color(*x, *y, color, lock_time);
+                    }
+                    moved = true;
+                }
+            }
+        }
+    }
+
+    fn can_chunk_fall(&self, chunk: &[(usize, usize)]) -> bool {
+        return chunk.iter().all(|(x, y)| {
+            let below = (*x, y + 1);
+            return below.1 < self.height()
+                && self.is_playable_at_xy(below.0, below.1)
+                && (self.figure_at_xy(below.0, below.1).is_none() || chunk.contains(&below));
+        });
+    }
+
+    fn get_chunks(&self, same_type_only: bool) -> Vec<Vec<(usize, usize)>> {
+        let mut visited = vec![vec![false; self.width()]; self.height()];
+        let mut chunks = vec![];
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if visited[y][x] || self.figure_at_xy(x, y).is_none() {
+                    continue;
+                }
+                let mut chunk = vec![];
+                let mut pending = vec![(x, y)];
+                visited[y][x] = true;
+                while let Some((cell_x, cell_y)) = pending.pop() {
+                    chunk.push((cell_x, cell_y));
+                    for (next_x, next_y) in self.get_neighbours(cell_x, cell_y) {
+                        let joins = match (
+                            self.figure_at_xy(cell_x, cell_y),
+                            self.figure_at_xy(next_x, next_y),
+                        ) {
+                            (Some(figure), Some(next)) => !same_type_only || figure == next,
+                            _ => false,
+                        };
+                        if joins && !visited[next_y][next_x] {
+                            visited[next_y][next_x] = true;
+                            pending.push((next_x, next_y));
+                        }
+                    }
+                }
+                chunks.push(chunk);
+            }
+        }
+        return chunks;
+    }
+
+    fn get_neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let (x, y, width, height) = (
+            x as i32,
+            y as i32,
+            self.width() as i32,
+            self.height() as i32,
+        );
+        return [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .iter()
+            .map(|(x, y)| self.wrapped_point(Point { x: *x, y: *y }))
+            .filter(|point| point.x >= 0 && point.x < width && point.y >= 0 && point.y < height)
+            .map(|point| (point.x as usize, point.y as usize))
+            .collect();
+    }
+
+    // Pushes the board contents up, adding one garbage line per hole at the bottom.
+    // Lines pushed beyond the top are discarded.
+    pub fn inserting_garbage_lines(&self, holes: &[usize]) -> Board {
+        let mut board = self.clone();
+        board.insert_garbage_lines(holes, 0.0);
+        return board;
+    }
+
+    fn get_garbage_line(&self, hole: usize) -> Vec<u8> {
+        return (0..self.width())
+            .map(|x| {
+                if x == hole {
+                    return EMPTY_COLOR;
+                }
+                return GARBAGE_COLOR;
+            })
+            .collect();
+    }
+
+    // IN PLACE CHANGES
+    // Counterparts of the methods above that change the board instead of
+    // copying it, for simulations making lots of changes.
+
+    // Writes a cell, ignoring cells outside the board. Fails, leaving the
+    // cell unchanged, when the board can't hold another figure type.
+    pub fn set(
+        &mut self,
+        x: usize,
+        y: usize,
+        figure_type: Option<FigureType>,
+    ) -> Result<(), TooManyFigureTypes> {
+        if !self.is_inside(x, y) {
+            return Ok(());
+        }
+        let color = self.palette_index(&figure_type)?;
+        let lock_time = self.lock_time_at_xy(x, y);
+        self.write_color(x, y, color, lock_time);
+        return Ok(());
+    }
+
+    pub fn set_locked(
+        &mut self,
+        x: usize,
+        y: usize,
+        figure_type: Option<FigureType>,
+        time: f64,
+    ) -> Result<(), TooManyFigureTypes> {
+        if !self.is_inside(x, y) {
+            return Ok(());
+        }
+        let color = self.palette_index(&figure_type)?;
+        self.write_color(x, y, color, time);
+        return Ok(());
+    }
+
+    fn clear_cell(&mut self, x: usize, y: usize) {
+        let lock_time = self.lock_time_at_xy(x, y);
+        self.write_color(x, y, EMPTY_COLOR, lock_time);
+    }
+
+    fn write_color(&mut self, x: usize, y: usize, color: u8, lock_time: f64) {
+        if !self.is_inside(x, y) {
+            return;
+        }
+        let (word, bit) = self.bit(x, y);
+        if color != EMPTY_COLOR {
+            self.filled[word] |= bit;
+        } else {
+            self.filled[word] &= !bit;
+        }
+        let index = self.index(x, y);
+        self.colors[index] = color;
+        self.lock_times[index] = lock_time;
+    }
+
+    fn set_cell_kind(&mut self, x: usize, y: usize, cell_kind: CellKind) {
+        if !self.is_inside(x, y) {
+            return;
+        }
+        let (word, bit) = self.bit(x, y);
+        self.obstacles[word] &= !bit;
+        self.masked[word] &= !bit;
+        match cell_kind {
+            CellKind::Playable => {}
+            CellKind::Obstacle => self.obstacles[word] |= bit,
+            CellKind::Masked => self.masked[word] |= bit,
+        }
+    }
+
+    // Removes the lines moving the lines above them down.
+    pub fn clear_rows(&mut self, lines: &[usize]) {
+        let mut target = self.height();
+        for source in (0..self.height()).rev() {
+            if lines.contains(&source) {
+                continue;
+            }
+            target -= 1;
+            if target != source {
+                self.move_row(source, target);
+            }
+        }
+        for line in 0..target {
+            self.empty_row(line);
+        }
+        self.clear_unplayable_cells();
+    }
+
+    pub fn clear_rows_with_gravity(&mut self, lines: &[usize], gravity: LineClearGravity) {
+        match gravity {
+            LineClearGravity::Naive => self.clear_rows(lines),
+            LineClearGravity::Sticky | LineClearGravity::Cascade => {
+                self.empty_rows(lines);
+                self.settle_chunks(gravity == LineClearGravity::Cascade);
+            }
+        }
+    }
+
+    pub fn clear_cells_with_gravity(
+        &mut self,
+        lines: &[usize],
+        cells: &[(usize, usize)],
+        gravity: LineClearGravity,
+    ) {
+        for (x, y) in cells {
+            self.write_color(*x, *y, EMPTY_COLOR, 0.0);
+        }
+        self.empty_rows(lines);
+        self.settle_chunks(gravity == LineClearGravity::Cascade);
+    }
+
+    // Pushes the contents up adding the rows at the bottom, the last one lowest.
+    // Lines pushed beyond the top are discarded. The new cells are locked at `time`.
+    // Fails, leaving the board unchanged, when it can't hold the figure types.
+    pub fn insert_rows_bottom(
+        &mut self,
+        rows: &[Vec<Option<FigureType>>],
+        time: f64,
+    ) -> Result<(), TooManyFigureTypes> {
+        let mut colors = vec![];
+        for row in rows {
+            let row_colors: Result<Vec<u8>, TooManyFigureTypes> = row
+                .iter()
+                .map(|figure_type| self.palette_index(figure_type))
+                .collect();
+            colors.push(row_colors?);
+        }
+        self.push_rows_bottom(&colors, false, time);
+        self.clear_unplayable_cells();
+        return Ok(());
+    }
+
+    pub fn insert_garbage_lines(&mut self, holes: &[usize], time: f64) {
+        let rows: Vec<Vec<u8>> = holes
+            .iter()
+            .map(|hole| self.get_garbage_line(*hole))
+            .collect();
+        self.push_rows_bottom(&rows, true, time);
+        self.clear_unplayable_cells();
+    }
+
+    // Moves every line up, leaving empty lines at the bottom.
+    pub fn shift_up(&mut self, count: usize) {
+        self.shift_rows_up(count);
+        self.clear_unplayable_cells();
+    }
+
+    fn push_rows_bottom(&mut self, rows: &[Vec<u8>], garbage: bool, time: f64) {
+        let count = rows.len().min(self.height());
+        self.shift_rows_up(count);
+        let first_line = self.height() - count;
+        for (line, row) in (first_line..self.height()).zip(rows) {
+            for (x, color) in row.iter().enumerate() {
+                self.write_color(x, line, *color, time);
+            }
+            self.garbage_lines[line] = garbage;
+        }
+    }
+
+    fn shift_rows_up(&mut self, count: usize) {
+        let count = count.min(self.height());
+        for line in count..self.height() {
+            self.move_row(line, line - count);
+        }
+        for line in self.height() - count..self.height() {
+            self.empty_row(line);
+        }
+    }
+
+    // Obstacles and masked cells stay in place while the contents move,
+    // so anything moved onto them is dropped.
+    fn clear_unplayable_cells(&mut self) {
+        for word in 0..self.filled.len() {
+            let mut dropped = self.filled[word] & (self.obstacles[word] | self.masked[word]);
+            self.filled[word] &= !dropped;
+            // Only the few dropped cells need their colors cleared.
+            while dropped != 0 {
+                let line = word / self.words;
+                let x = word % self.words * WORD_BITS + dropped.trailing_zeros() as usize;
+                let index = self.index(x, line);
+                self.colors[index] = EMPTY_COLOR;
+                dropped &= dropped - 1;
+            }
+        }
+    }
+
+    fn move_row(&mut self, from: usize, to: usize) {
+        let width = self.width();
+        let words = self.words;
+        self.filled
+            .copy_within(from * words..(from + 1) * words, to * words);
+        self.garbage_lines[to] = self.garbage_lines[from];
+        self.colors
+            .copy_within(from * width..(from + 1) * width, to * width);
+        self.lock_times
+            .copy_within(from * width..(from + 1) * width, to * width);
+    }
+
+    fn empty_row(&mut self, line: usize) {
+        let width = self.width();
+        let words = self.row_words(line);
+        self.filled[words].fill(0);
+        self.garbage_lines[line] = false;
+        self.colors[line * width..(line + 1) * width].fill(0);
+        self.lock_times[line * width..(line + 1) * width].fill(0.0);
+    }
+
+    fn palette_index(
+        &mut self,
+        figure_type: &Option<FigureType>,
+    ) -> Result<u8, TooManyFigureTypes> {
+        let position = self
+            .palette
+            .iter()
+            .position(|entry| is_same_type(entry, figure_type));
+        if let Some(index) = position {
+            return Ok(index as u8);
+        }
+        if self.palette.len() > u8::MAX as usize {
+            return Err(TooManyFigureTypes);
+        }
+        self.palette.push(figure_type.clone());
+        return Ok((self.palette.len() - 1) as u8);
+    }
+
+    fn blocked_word(&self, word: usize) -> u64 {
+        return self.filled[word] | self.obstacles[word] | self.masked[word];
+    }
+
+    // The word holding the cell and the bit of the cell in it.
+    fn bit(&self, x: usize, y: usize) -> (usize, u64) {
+        return (y * self.words + x / WORD_BITS, 1 << (x % WORD_BITS));
+    }
+
+    fn row_words(&self, line: usize) -> std::ops::Range<usize> {
+        return line * self.words..(line + 1) * self.words;
+    }
+
+    // The bits of the columns inside the board, for the nth word of a row.
+    fn word_mask(&self, column_word: usize) -> u64 {
+        let columns = self.width() - column_word * WORD_BITS;
+        if columns >= WORD_BITS {
+            return u64::MAX;
+        }
+        return (1 << columns) - 1;
+    }
+
+    fn is_inside(&self, x: usize, y: usize) -> bool {
+        return x < self.width() && y < self.height();
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        return y * self.width() + x;
     }
 }
 
+// Custom types are compared by definition pointer, so finding a palette
+// entry never compares whole definitions.
+fn is_same_type(first: &Option<FigureType>, second: &Option<FigureType>) -> bool {
+    return match (first, second) {
+        (Some(FigureType::Custom(first)), Some(FigureType::Custom(second))) => {
+            Rc::ptr_eq(first, second)
+        }
+        _ => first == second,
+    };
+}
+
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast, clippy::useless_vec)]
 mod board_tests {
+    use super::super::PieceDefinition;
     use super::*;
 
+    fn get_rows(board: &Board) -> Vec<Vec<Option<FigureType>>> {
+        return (0..board.height())
+            .map(|line| board.get_line(line).unwrap_or_default())
+            .collect();
+    }
+
     #[test]
     fn test_height() {
         let height = 10;
@@ -98,13 +834,20 @@ mod board_tests {
         assert_eq!(board.width(), width);
     }
     #[test]
+    fn test_from_rows() {
+        let rows = vec![vec![None, Some(FigureType::O)], vec![None, None]];
+        let board = Board::from_rows(rows.clone());
+        assert_eq!(get_rows(&board), rows);
+        assert_eq!(board.garbage_lines_count(), 0);
+    }
+    #[test]
     fn test_replacing_figure() {
         let board = Board::new(&Size {
             height: 2,
             width: 1,
         });
         let replaced_board = board.replacing_figure_at_xy(0, 0, Some(FigureType::I));
-        assert!(replaced_board.matrix.at_xy(0, 0).is_some());
+        assert!(replaced_board.figure_at_xy(0, 0).is_some());
     }
     #[test]
     fn test_does_not_contains() {
@@ -136,18 +879,424 @@ mod board_tests {
         let board_03 = board_02.replacing_figure_at_xy(0, 3, Some(FigureType::I));
         let final_board = board_03.removing_lines(&vec![3]);
 
-        let expectation = Matrix::new(vec![
+        let expectation = vec![
             vec![None],
             vec![Some(FigureType::I)],
             vec![None],
             vec![None],
-        ]);
+        ];
 
-        assert_eq!(final_board.matrix, expectation);
+        assert_eq!(get_rows(&final_board), expectation);
 
         let final_board_02 = board_03.removing_lines(&vec![0, 3]);
-        let expectation_02 = Matrix::new(vec![vec![None], vec![None], vec![None], vec![None]]);
+        let expectation_02 = vec![vec![None], vec![None], vec![None], vec![None]];
+
+        assert_eq!(get_rows(&final_board_02), expectation_02);
+    }
+    #[test]
+    fn test_is_line_empty() {
+        let board = Board::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        let final_board = board.replacing_figure_at_xy(1, 1, Some(FigureType::S));
+        assert!(board.is_empty());
+        assert!(final_board.is_line_empty(0));
+        assert!(!final_board.is_line_empty(1));
+        assert!(!final_board.is_empty());
+    }
+    #[test]
+    fn test_inserting_garbage_lines() {
+        let board = Board::new(&Size {
+            height: 3,
+            width: 3,
+        });
+        let board_02 = board.replacing_figure_at_xy(1, 2, Some(FigureType::T));
+        let final_board = board_02.inserting_garbage_lines(&[0]);
 
-        assert_eq!(final_board_02.matrix, expectation_02);
+        let expectation = vec![
+            vec![None, None, None],
+            vec![None, Some(FigureType::T), None],
+            vec![None, Some(FigureType::Garbage), Some(FigureType::Garbage)],
+        ];
+
+        assert_eq!(get_rows(&final_board), expectation);
+        assert!(!final_board.is_garbage_line(1));
+        assert!(final_board.is_garbage_line(2));
+        assert_eq!(final_board.garbage_lines_count(), 1);
+    }
+    #[test]
+    fn test_locking_figure_keeps_lock_time() {
+        let board = Board::new(&Size {
+            height: 3,
+            width: 2,
+        });
+        let locked_board = board.locking_figure_at_xy(1, 1, FigureType::Z, 2.5);
+        assert_eq!(*locked_board.figure_at_xy(1, 1), Some(FigureType::Z));
+        assert_eq!(locked_board.lock_time_at_xy(1, 1), 2.5);
+        assert_eq!(locked_board.lock_time_at_xy(0, 1), 0.0);
+
+        let final_board = locked_board.removing_lines(&[2]);
+        assert_eq!(final_board.lock_time_at_xy(1, 2), 2.5);
+        assert_eq!(final_board.lock_time_at_xy(1, 1), 0.0);
+    }
+    #[test]
+    fn test_obstacles_are_contained() {
+        let board = Board::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        let obstacle_board = board.replacing_cell_kind_at_xy(1, 1, CellKind::Obstacle);
+        assert!(obstacle_board.contains(Point { x: 1, y: 1 }));
+        assert!(!obstacle_board.contains(Point { x: 0, y: 1 }));
+        assert!(obstacle_board.is_empty());
+    }
+    #[test]
+    fn test_line_with_masked_cells_completes() {
+        let board = Board::with_cell_kinds(vec![
+            vec![CellKind::Masked, CellKind::Playable, CellKind::Masked],
+            vec![CellKind::Masked, CellKind::Masked, CellKind::Masked],
+        ]);
+        assert!(!board.is_line_complete(0));
+        assert!(!board.is_line_complete(1));
+        let filled_board = board.replacing_figure_at_xy(1, 0, Some(FigureType::T));
+        assert!(filled_board.is_line_complete(0));
+    }
+    #[test]
+    fn test_removing_lines_keeps_obstacles() {
+        let board = Board::new(&Size {
+            height: 3,
+            width: 2,
+        })
+        .replacing_cell_kind_at_xy(0, 1, CellKind::Obstacle)
+        .replacing_figure_at_xy(0, 0, Some(FigureType::I))
+        .replacing_figure_at_xy(1, 0, Some(FigureType::I))
+        .replacing_figure_at_xy(0, 2, Some(FigureType::J))
+        .replacing_figure_at_xy(1, 2, Some(FigureType::J));
+        let final_board = board.removing_lines(&[2]);
+
+        assert_eq!(final_board.cell_kind_at_xy(0, 1), CellKind::Obstacle);
+        assert_eq!(*final_board.figure_at_xy(0, 1), None);
+        assert_eq!(*final_board.figure_at_xy(1, 1), Some(FigureType::I));
+        assert!(final_board.is_line_empty(2));
+    }
+    #[test]
+    fn test_wrapping_board_contains() {
+        let board = Board::new(&Size {
+            height: 2,
+            width: 4,
+        })
+        .replacing_figure_at_xy(0, 1, Some(FigureType::I));
+        assert!(!board.contains(Point { x: 4, y: 1 }));
+        let wrapping_board = board.wrapping_around(true);
+        assert!(wrapping_board.contains(Point { x: 4, y: 1 }));
+        assert!(wrapping_board.contains(Point { x: -4, y: 1 }));
+        assert!(!wrapping_board.contains(Point { x: -1, y: 1 }));
+        assert!(wrapping_board.removing_lines(&[0]).is_wrapping_around());
+    }
+    fn get_floating_board() -> Board {
+        // . . J J
+        // T . . J
+        // I I I I <- cleared
+        // T . . .
+        let board = Board::new(&Size {
+            height: 4,
+            width: 4,
+        })
+        .replacing_figure_at_xy(2, 0, Some(FigureType::J))
+        .replacing_figure_at_xy(3, 0, Some(FigureType::J))
+        .replacing_figure_at_xy(3, 1, Some(FigureType::J))
+        .replacing_figure_at_xy(0, 1, Some(FigureType::T))
+        .replacing_figure_at_xy(0, 3, Some(FigureType::T));
+        return (0..4).fold(board, |board, x| {
+            board.replacing_figure_at_xy(x, 2, Some(FigureType::I))
+        });
+    }
+    // Letters are figure types, G is garbage and . an empty cell.
+    fn get_rows_from(rows: &[&str]) -> Vec<Vec<Option<FigureType>>> {
+        return rows
+            .iter()
+            .map(|row| {
+                return row
+                    .chars()
+                    .map(|cell| match cell {
+                        'I' => Some(FigureType::I),
+                        'J' => Some(FigureType::J),
+                        'T' => Some(FigureType::T),
+                        'G' => Some(FigureType::Garbage),
+                        _ => None,
+                    })
+                    .collect();
+            })
+            .collect();
+    }
+    fn get_board_from(rows: &[&str]) -> Board {
+        let rows = rows
+            .iter()
+            .map(|row| {
+                return row
+                    .chars()
+                    .map(|cell| {
+                        if cell == 'X' {
+                            Some(FigureType::I)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+            })
+            .collect();
+        return Board::from_rows(rows);
+    }
+    #[test]
+    fn test_column_heights_and_bumpiness() {
+        let board = get_board_from(&["....", "X...", "X.X.", "XX.X"]);
+        assert_eq!(board.column_heights(), vec![3, 1, 2, 1]);
+        assert_eq!(board.bumpiness(), 2 + 1 + 1);
+    }
+    #[test]
+    fn test_holes_and_covered_cells() {
+        let board = get_board_from(&["X...", "XX..", ".X.X", "X..X"]);
+        assert_eq!(board.holes(), 2);
+        // Column 0 covers the hole on row 2, column 1 the one on row 3.
+        assert_eq!(board.covered_cells(), 2 + 2);
+    }
+    #[test]
+    fn test_transitions() {
+        let board = get_board_from(&["....", "X...", "X.X.", "XX.X"]);
+        assert_eq!(board.row_transitions(), 2 + 2 + 4 + 2);
+        assert_eq!(board.column_transitions(), 1 + 1 + 3 + 1);
+    }
+    #[test]
+    fn test_well_depths() {
+        let board = get_board_from(&["....", ".X..", "XX..", "XXX."]);
+        assert_eq!(board.well_depths(), vec![1, 0, 0, 1]);
+        assert_eq!(board.wrapping_around(true).well_depths(), vec![0, 0, 0, 1]);
+    }
+    #[test]
+    fn test_t_slots() {
+        let board = get_board_from(&["XX....", "XX....", "X...XX", "XX.XXX"]);
+        assert_eq!(board.t_slots(), vec![Point { x: 2, y: 2 }]);
+        assert!(Board::new(&Size {
+            height: 4,
+            width: 4
+        })
+        .t_slots()
+        .is_empty());
+    }
+    #[test]
+    fn test_too_many_figure_types() {
+        let mut board = Board::new(&Size {
+            height: 16,
+            width: 16,
+        });
+        let color = FigureType::I.color();
+        let types: Vec<FigureType> = (0..254)
+            .map(|_| {
+                FigureType::Custom(Rc::new(PieceDefinition::new(
+                    "X",
+                    vec![vec![1]],
+                    color.clone(),
+                )))
+            })
+            .collect();
+        for (index, figure_type) in types.iter().enumerate() {
+            board
+                .set(index % 16, index / 16, Some(figure_type.clone()))
+                .unwrap();
+        }
+        // Equal definitions are still different types, but the same one is reused.
+        assert_eq!(board.palette.len(), 256);
+        assert_eq!(board.set(15, 15, Some(types[0].clone())), Ok(()));
+        assert_eq!(*board.figure_at_xy(15, 15), Some(types[0].clone()));
+        let extra = FigureType::Custom(Rc::new(PieceDefinition::new("Y", vec![vec![1]], color)));
+        assert_eq!(board.set(15, 15, Some(extra)), Err(TooManyFigureTypes));
+        assert_eq!(*board.figure_at_xy(15, 15), Some(types[0].clone()));
+        board.insert_garbage_lines(&[0], 0.0);
+        assert!(board.is_garbage_line(15));
+    }
+    #[test]
+    fn test_wide_board() {
+        let width = 100;
+        let mut board = Board::new(&Size { height: 3, width });
+        board.set(70, 1, Some(FigureType::T)).unwrap();
+        board.set_cell_kind(99, 2, CellKind::Obstacle);
+        assert!(board.contains(Point { x: 70, y: 1 }));
+        assert!(!board.contains(Point { x: 6, y: 1 }));
+        assert_eq!(board.cell_kind_at_xy(99, 2), CellKind::Obstacle);
+        assert!(board.contains(Point { x: 99, y: 2 }));
+        assert!(!board.contains(Point { x: 35, y: 2 }));
+        for x in 0..width - 1 {
+            board.set(x, 2, Some(FigureType::I)).unwrap();
+        }
+        assert!(board.is_line_complete(2));
+        board.set(99, 1, Some(FigureType::J)).unwrap();
+        board.clear_rows(&[2]);
+        assert_eq!(*board.figure_at_xy(99, 2), None);
+        assert!(!board.contains(Point { x: 98, y: 2 }));
+        assert_eq!(*board.figure_at_xy(70, 2), Some(FigureType::T));
+        assert!(board.is_line_empty(1));
+        assert_eq!(board.column_heights()[70], 1);
+        assert_eq!(board.cell_kind_at_xy(99, 2), CellKind::Obstacle);
+    }
+    #[test]
+    fn test_removing_lines_moves_types_and_lock_times() {
+        let board = Board::new(&Size {
+            height: 3,
+            width: 2,
+        })
+        .locking_figure_at_xy(0, 0, FigureType::T, 2.0)
+        .locking_figure_at_xy(0, 2, FigureType::O, 1.0)
+        .locking_figure_at_xy(1, 2, FigureType::O, 1.0)
+        .removing_lines(&[2]);
+        assert_eq!(*board.figure_at_xy(0, 1), Some(FigureType::T));
+        assert_eq!(board.lock_time_at_xy(0, 1), 2.0);
+        assert!(board.is_line_empty(0));
+        assert!(board.is_line_empty(2));
+    }
+    #[test]
+    fn test_widest_board() {
+        let width = 64;
+        let board = (0..width).fold(Board::new(&Size { height: 2, width }), |board, x| {
+            board.replacing_figure_at_xy(x, 1, Some(FigureType::I))
+        });
+        assert!(board.is_line_complete(1));
+        assert!(board.contains(Point { x: 63, y: 1 }));
+        assert!(!board.contains(Point { x: 64, y: 1 }));
+    }
+    #[test]
+    fn test_sticky_gravity_drops_chunks() {
+        let board =
+            get_floating_board().removing_lines_with_gravity(&[2], LineClearGravity::Sticky);
+        let expectation = vec![
+            vec![None, None, None, None],
+            vec![None, None, None, None],
+            vec![
+                Some(FigureType::T),
+                None,
+                Some(FigureType::J),
+                Some(FigureType::J),
+            ],
+            vec![Some(FigureType::T), None, None, Some(FigureType::J)],
+        ];
+        assert_eq!(get_rows(&board), expectation);
+    }
+    #[test]
+    fn test_cascade_gravity_splits_figure_types() {
+        let board = Board::new(&Size {
+            height: 3,
+            width: 2,
+        })
+        .replacing_figure_at_xy(0, 0, Some(FigureType::S))
+        .replacing_figure_at_xy(1, 0, Some(FigureType::Z))
+        .replacing_figure_at_xy(1, 1, Some(FigureType::Z))
+        .replacing_figure_at_xy(1, 2, Some(FigureType::Z));
+        let sticky = board.removing_lines_with_gravity(&[], LineClearGravity::Sticky);
+        assert_eq!(*sticky.figure_at_xy(0, 0), Some(FigureType::S));
+        let cascade = board.removing_lines_with_gravity(&[], LineClearGravity::Cascade);
+        assert_eq!(*cascade.figure_at_xy(0, 2), Some(FigureType::S));
+        assert_eq!(*cascade.figure_at_xy(1, 0), Some(FigureType::Z));
+    }
+    #[test]
+    fn test_matching_groups() {
+        let board = get_floating_board();
+        let groups = board.matching_groups(3);
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().any(|group| group.len() == 4));
+        assert!(groups.iter().any(|group| group.len() == 3));
+        assert_eq!(board.matching_groups(4).len(), 1);
+    }
+    #[test]
+    fn test_removing_cells_drops_blocks() {
+        let board = get_floating_board().removing_cells_with_gravity(
+            &[],
+            &[(0, 1), (0, 2)],
+            LineClearGravity::Naive,
+        );
+        assert_eq!(*board.figure_at_xy(0, 3), Some(FigureType::T));
+        assert_eq!(*board.figure_at_xy(0, 1), None);
+        assert_eq!(*board.figure_at_xy(1, 3), Some(FigureType::I));
+        assert_eq!(*board.figure_at_xy(2, 1), Some(FigureType::J));
+    }
+    #[test]
+    fn test_naive_gravity_shifts_rows() {
+        let board = get_floating_board().removing_lines_with_gravity(&[2], LineClearGravity::Naive);
+        assert_eq!(*board.figure_at_xy(0, 2), Some(FigureType::T));
+        assert_eq!(*board.figure_at_xy(2, 1), Some(FigureType::J));
+    }
+    #[test]
+    fn test_removing_lines_keeps_garbage_tracking() {
+        let board = Board::new(&Size {
+            height: 4,
+            width: 2,
+        });
+        let garbage_board = board.inserting_garbage_lines(&[0, 1]);
+        let final_board = garbage_board.removing_lines(&[3]);
+
+        assert_eq!(final_board.garbage_lines_count(), 1);
+        assert!(final_board.is_garbage_line(3));
+        assert!(!final_board.is_garbage_line(0));
+    }
+    #[test]
+    fn test_in_place_changes() {
+        let mut board = get_board_from(&["....", "X...", "XXXX", ".X.."]);
+        board.set(3, 0, Some(FigureType::T)).unwrap();
+        board.clear_rows(&[2]);
+        let expectation = get_board_from(&["....", "....", "X...", ".X.."]).replacing_figure_at_xy(
+            3,
+            1,
+            Some(FigureType::T),
+        );
+        assert_eq!(get_rows(&board), get_rows(&expectation));
+        board.shift_up(1);
+        assert!(board.is_line_empty(3));
+        assert_eq!(*board.figure_at_xy(3, 0), Some(FigureType::T));
+        board
+            .insert_rows_bottom(&[vec![Some(FigureType::O); 4]], 2.5)
+            .unwrap();
+        assert_eq!(board.lock_time_at_xy(0, 3), 2.5);
+        assert!(board.is_line_complete(3));
+        assert!(!board.is_garbage_line(3));
+        assert_eq!(board.figure_at_xy(3, 0), &None);
+    }
+    #[test]
+    fn test_in_place_changes_results() {
+        let board = get_floating_board();
+        let shifted = get_rows_from(&["....", "..JJ", "T..J", "T..."]);
+        let chunks = get_rows_from(&["....", "....", "T.JJ", "T..J"]);
+        let dropped = get_rows_from(&["....", "..JJ", "T..J", "IIII"]);
+        for (gravity, lines_cleared) in [
+            (LineClearGravity::Naive, &shifted),
+            (LineClearGravity::Sticky, &chunks),
+            (LineClearGravity::Cascade, &chunks),
+        ] {
+            let mut changed = board.clone();
+            changed.clear_rows_with_gravity(&[2], gravity);
+            assert_eq!(get_rows(&changed), *lines_cleared);
+            let copy = board.removing_lines_with_gravity(&[2], gravity);
+            assert_eq!(get_rows(&copy), *lines_cleared);
+            let mut changed = board.clone();
+            changed.clear_cells_with_gravity(&[], &[(0, 3)], gravity);
+            assert_eq!(get_rows(&changed), dropped);
+            let copy = board.removing_cells_with_gravity(&[], &[(0, 3)], gravity);
+            assert_eq!(get_rows(&copy), dropped);
+        }
+        let garbage = get_rows_from(&["IIII", "T...", "G.GG", "GG.G"]);
+        let mut changed = board.clone();
+        changed.insert_garbage_lines(&[1, 2], 0.0);
+        assert_eq!(get_rows(&changed), garbage);
+        assert_eq!(get_rows(&board.inserting_garbage_lines(&[1, 2])), garbage);
+        assert_eq!(changed.garbage_lines_count(), 2);
+    }
+    #[test]
+    fn test_shift_up_drops_blocks_on_obstacles() {
+        let mut board = get_board_from(&["....", "....", "XX.."]).replacing_cell_kind_at_xy(
+            0,
+            1,
+            CellKind::Obstacle,
+        );
+        board.shift_up(1);
+        assert_eq!(board.figure_at_xy(0, 1), &None);
+        assert_eq!(board.figure_at_xy(1, 1), &Some(FigureType::I));
     }
 }
@@ -0,0 +1,465 @@
+use super::{
+    Action, Board, FigureType, Game, GameMode, PieceSet, Placement, Point, SeededRandomizer,
+    SevenBagGenerator, Size,
+};
+
+const FRAME_TIME: f64 = 1.0 / 60.0; //secs
+
+// Per-frame action indices: doing nothing and then every `Action`.
+const FRAME_ACTIONS: [Option<Action>; 6] = [
+    None,
+    Some(Action::MoveLeft),
+    Some(Action::MoveRight),
+    Some(Action::MoveDown),
+    Some(Action::Rotate),
+    Some(Action::HardDrop),
+];
+
+// - Frame: every step performs one input (or none) and advances one frame.
+// - Placement: every step locks the active figure at one of `placements()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionSpace {
+    Frame,
+    Placement,
+}
+
+// Parts of the game included in the observations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObservationEncoding {
+    pub board: bool,
+    pub active_figure: bool,
+    pub piece: bool,
+    pub queue: bool,
+}
+
+impl Default for ObservationEncoding {
+    fn default() -> ObservationEncoding {
+        return ObservationEncoding {
+            board: true,
+            active_figure: true,
+            piece: true,
+            queue: true,
+        };
+    }
+}
+
+// - board: bitmap of the locked cells, row by row.
+// - active_figure: bitmap of the cells taken by the active figure.
+// - piece, queue: one-hot of the active and next figures in the piece set.
+// Parts left out of the encoding are empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub board: Vec<u8>,
+    pub active_figure: Vec<u8>,
+    pub piece: Vec<u8>,
+    pub queue: Vec<u8>,
+}
+
+impl Observation {
+    // All the parts one after the other.
+    pub fn to_features(&self) -> Vec<f32> {
+        return self
+            .board
+            .iter()
+            .chain(&self.active_figure)
+            .chain(&self.piece)
+            .chain(&self.queue)
+            .map(|value| *value as f32)
+            .collect();
+    }
+}
+
+// Reward of every step, adding the changes since the previous step times
+// their weight. Holes, bumpiness and height are usually penalized with
+// negative weights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rewards {
+    pub score: f64,
+    pub lines: f64,
+    pub holes: f64,
+    pub bumpiness: f64,
+    pub height: f64,
+    pub step: f64,
+    pub game_over: f64,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        return Rewards {
+            score: 0.0,
+            lines: 1.0,
+            holes: 0.0,
+            bumpiness: 0.0,
+            height: 0.0,
+            step: 0.0,
+            game_over: -1.0,
+        };
+    }
+}
+
+// The board metrics rewarded by a step. Only the ones with a weight are
+// computed, the others stay at zero.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct BoardMetrics {
+    holes: f64,
+    bumpiness: f64,
+    height: f64,
+}
+
+impl BoardMetrics {
+    fn measure(board: &Board, rewards: &Rewards) -> BoardMetrics {
+        let mut metrics = BoardMetrics::default();
+        if rewards.holes != 0.0 {
+            metrics.holes = board.holes() as f64;
+        }
+        if rewards.bumpiness != 0.0 {
+            metrics.bumpiness = board.bumpiness() as f64;
+        }
+        if rewards.height != 0.0 {
+            metrics.height = stack_height(board) as f64;
+        }
+        return metrics;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepInfo {
+    pub lines: u32,
+    pub score: u64,
+    pub pieces: u32,
+    pub invalid_action: bool,
+}
+
+// Gym style wrapper around a `Game`. Games are seeded on reset, so the same
+// seed and actions always play the same game.
+pub struct Environment {
+    size: Size,
+    mode: GameMode,
+    action_space: ActionSpace,
+    piece_set: PieceSet,
+    encoding: ObservationEncoding,
+    rewards: Rewards,
+    frame_time: f64,
+    game: Game,
+    placements: Vec<Placement>,
+    metrics: BoardMetrics,
+}
+
+impl Environment {
+    pub fn new(size: &Size, mode: GameMode, action_space: ActionSpace) -> Environment {
+        let piece_set = PieceSet::tetrominoes();
+        let game = Environment::seeded_game(size, &mode, &piece_set, 0);
+        let mut environment = Environment {
+            size: size.clone(),
+            mode,
+            action_space,
+            piece_set,
+            encoding: ObservationEncoding::default(),
+            rewards: Rewards::default(),
+            frame_time: FRAME_TIME,
+            game,
+            placements: vec![],
+            metrics: BoardMetrics::default(),
+        };
+        environment.update_placements();
+        return environment;
+    }
+
+    fn seeded_game(size: &Size, mode: &GameMode, piece_set: &PieceSet, seed: u64) -> Game {
+        let generator = Box::new(SevenBagGenerator::with_piece_set(seed, piece_set.clone()));
+        let randomizer = Box::new(SeededRandomizer::new(seed));
+        return Game::with_generator(size, randomizer, mode.clone(), generator);
+    }
+
+    // Takes effect on the next reset.
+    pub fn set_piece_set(&mut self, piece_set: PieceSet) {
+        self.piece_set = piece_set;
+    }
+
+    pub fn set_observation_encoding(&mut self, encoding: ObservationEncoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn set_rewards(&mut self, rewards: Rewards) {
+        self.rewards = rewards;
+        self.metrics = BoardMetrics::measure(self.game.get_board(), &self.rewards);
+    }
+
+    pub fn set_frame_time(&mut self, frame_time: f64) {
+        self.frame_time = frame_time;
+    }
+
+    pub fn get_action_space(&self) -> ActionSpace {
+        return self.action_space;
+    }
+
+    // Valid action indices are 0..action_count(). With placements the count
+    // changes on every step.
+    pub fn action_count(&self) -> usize {
+        return match self.action_space {
+            ActionSpace::Frame => FRAME_ACTIONS.len(),
+            ActionSpace::Placement => self.placements.len(),
+        };
+    }
+
+    pub fn placements(&self) -> &[Placement] {
+        return &self.placements;
+    }
+
+    pub fn get_game(&self) -> &Game {
+        return &self.game;
+    }
+
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = Environment::seeded_game(&self.size, &self.mode, &self.piece_set, seed);
+        self.update_placements();
+        self.metrics = BoardMetrics::measure(self.game.get_board(), &self.rewards);
+        return self.observation();
+    }
+
+    pub fn step(&mut self, action: usize) -> (Observation, f64, bool, StepInfo) {
+        let (lines, score) = (self.game.get_lines(), self.game.get_score());
+        let was_game_over = self.game.is_game_over();
+        let mut invalid_action = action >= self.action_count();
+        if !self.is_done() && !invalid_action {
+            invalid_action = !match self.action_space {
+                ActionSpace::Frame => self.step_frame(FRAME_ACTIONS[action]),
+                ActionSpace::Placement => self.step_placement(action),
+            };
+        }
+        self.update_placements();
+        let done = self.is_done();
+        let rewards = &self.rewards;
+        // The metrics after this step are the ones before the next step.
+        let before = self.metrics;
+        let after = BoardMetrics::measure(self.game.get_board(), rewards);
+        let mut reward = rewards.step
+            + rewards.score * (self.game.get_score() - score) as f64
+            + rewards.lines * (self.game.get_lines() - lines) as f64
+            + rewards.holes * (after.holes - before.holes)
+            + rewards.bumpiness * (after.bumpiness - before.bumpiness)
+            + rewards.height * (after.height - before.height);
+        if self.game.is_game_over() && !was_game_over {
+            reward += rewards.game_over;
+        }
+        self.metrics = after;
+        let info = StepInfo {
+            lines: self.game.get_lines() - lines,
+            score: self.game.get_score(),
+            pieces: self.game.get_pieces(),
+            invalid_action,
+        };
+        return (self.observation(), reward, done, info);
+    }
+
+    // A game with no placements left can't continue either.
+    pub fn is_done(&self) -> bool {
+        return self.game.is_finished()
+            || (self.action_space == ActionSpace::Placement && self.placements.is_empty());
+    }
+
+    fn step_frame(&mut self, action: Option<Action>) -> bool {
+        if let Some(action) = action {
+            self.game.perform(action);
+        }
+        self.game.update(self.frame_time);
+        return true;
+    }
+
+    // The figure is locked right away, waiting for the next one to spawn
+    // when the mode has a spawn delay.
+    fn step_placement(&mut self, index: usize) -> bool {
+        let actions = match self.game.path_to(&self.placements[index]) {
+            Some(actions) => actions,
+            None => return false,
+        };
+        for action in actions {
+            self.game.perform(action);
+        }
+        if let Some(delay) = self.game.get_spawn_delay() {
+            self.game.update(delay);
+        }
+        return true;
+    }
+
+    fn update_placements(&mut self) {
+        self.placements = match self.action_space {
+            ActionSpace::Placement if !self.game.is_finished() => self.game.reachable_placements(),
+            _ => vec![],
+        };
+    }
+
+    pub fn observation(&self) -> Observation {
+        let encoding = &self.encoding;
+        let board = self.game.get_board();
+        let (width, height) = (board.width(), board.height());
+        let mut observation = Observation {
+            board: vec![],
+            active_figure: vec![],
+            piece: vec![],
+            queue: vec![],
+        };
+        if encoding.board {
+            observation.board = (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| board.figure_at_xy(x, y).is_some() as u8)
+                .collect();
+        }
+        if encoding.active_figure {
+            let mut cells = vec![0; width * height];
+            for point in self.game.get_active_figure().to_cartesian() {
+                let Point { x, y } = board.wrapped_point(point);
+                if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                    cells[y as usize * width + x as usize] = 1;
+                }
+            }
+            observation.active_figure = cells;
+        }
+        if encoding.piece {
+            let active = self.game.get_active_figure().get_type();
+            observation.piece = self.one_hot(Some(&active));
+        }
+        if encoding.queue {
+            let next = self.game.get_next_figure().map(|next| next.get_type());
+            observation.queue = self.one_hot(next.as_ref());
+        }
+        return observation;
+    }
+
+    fn one_hot(&self, figure_type: Option<&FigureType>) -> Vec<u8> {
+        return self
+            .piece_set
+            .get_figures()
+            .iter()
+            .map(|figure| (Some(figure) == figure_type) as u8)
+            .collect();
+    }
+}
+
+fn stack_height(board: &Board) -> usize {
+    return board.column_heights().into_iter().max().unwrap_or(0);
+}
+
+#[cfg(test)]
+mod env_tests {
+    use super::*;
+
+    fn get_environment(action_space: ActionSpace) -> Environment {
+        let size = Size {
+            height: 20,
+            width: 10,
+        };
+        return Environment::new(&size, GameMode::Endless, action_space);
+    }
+
+    #[test]
+    fn test_observation_sizes() {
+        let mut environment = get_environment(ActionSpace::Frame);
+        let observation = environment.reset(1);
+        assert_eq!(observation.board.len(), 200);
+        assert_eq!(observation.active_figure.iter().sum::<u8>(), 4);
+        assert_eq!(observation.piece.iter().sum::<u8>(), 1);
+        assert_eq!(observation.queue.iter().sum::<u8>(), 1);
+        assert_eq!(observation.to_features().len(), 200 + 200 + 7 + 7);
+    }
+    #[test]
+    fn test_observation_encoding() {
+        let mut environment = get_environment(ActionSpace::Frame);
+        environment.set_observation_encoding(ObservationEncoding {
+            board: true,
+            active_figure: false,
+            piece: false,
+            queue: true,
+        });
+        let observation = environment.reset(1);
+        assert!(observation.active_figure.is_empty());
+        assert!(observation.piece.is_empty());
+        assert_eq!(observation.to_features().len(), 200 + 7);
+    }
+    #[test]
+    fn test_reset_is_deterministic() {
+        let mut environment = get_environment(ActionSpace::Placement);
+        let first = environment.reset(42);
+        let first_steps: Vec<Observation> = (0..5).map(|_| environment.step(0).0).collect();
+        assert_eq!(environment.reset(42), first);
+        let second_steps: Vec<Observation> = (0..5).map(|_| environment.step(0).0).collect();
+        assert_eq!(first_steps, second_steps);
+    }
+    #[test]
+    fn test_placement_step_locks_figure() {
+        let mut environment = get_environment(ActionSpace::Placement);
+        environment.reset(3);
+        assert!(environment.action_count() > 0);
+        let (observation, _, done, info) = environment.step(0);
+        assert!(!done);
+        assert_eq!(info.pieces, 1);
+        assert_eq!(observation.board.iter().sum::<u8>(), 4);
+    }
+    #[test]
+    fn test_frame_step_moves_figure() {
+        let mut environment = get_environment(ActionSpace::Frame);
+        let before = environment.reset(3);
+        let (after, _, _, info) = environment.step(2);
+        assert!(!info.invalid_action);
+        assert_ne!(before.active_figure, after.active_figure);
+        let (_, _, _, info) = environment.step(5);
+        assert_eq!(info.pieces, 1);
+    }
+    #[test]
+    fn test_invalid_action() {
+        let mut environment = get_environment(ActionSpace::Frame);
+        environment.reset(3);
+        let (_, _, _, info) = environment.step(6);
+        assert!(info.invalid_action);
+    }
+    #[test]
+    fn test_game_over_reward() {
+        let mut environment = get_environment(ActionSpace::Frame);
+        environment.reset(3);
+        let mut steps = 0;
+        loop {
+            let (_, reward, done, _) = environment.step(5);
+            steps += 1;
+            if done {
+                assert_eq!(reward, -1.0);
+                break;
+            }
+            assert_eq!(reward, 0.0);
+        }
+        assert!(steps > 1);
+        assert!(environment.get_game().is_game_over());
+        let (_, reward, done, _) = environment.step(5);
+        assert!(done);
+        assert_eq!(reward, 0.0);
+    }
+    #[test]
+    fn test_shaped_rewards() {
+        let mut environment = get_environment(ActionSpace::Placement);
+        environment.set_rewards(Rewards {
+            step: 0.5,
+            height: -1.0,
+            ..Rewards::default()
+        });
+        environment.reset(3);
+        let (_, reward, _, _) = environment.step(0);
+        let height = stack_height(environment.get_game().get_board()) as f64;
+        assert_eq!(reward, 0.5 - height);
+        let (_, reward, _, _) = environment.step(0);
+        let new_height = stack_height(environment.get_game().get_board()) as f64;
+        assert_eq!(reward, 0.5 - (new_height - height));
+    }
+    #[test]
+    fn test_unweighted_metrics_are_skipped() {
+        let mut environment = get_environment(ActionSpace::Placement);
+        environment.set_rewards(Rewards {
+            holes: -1.0,
+            ..Rewards::default()
+        });
+        environment.reset(3);
+        environment.step(0);
+        assert_eq!(environment.metrics.bumpiness, 0.0);
+        assert_eq!(environment.metrics.height, 0.0);
+        let holes = environment.get_game().get_board().holes() as f64;
+        assert_eq!(environment.metrics.holes, holes);
+    }
+}
@@ -1,20 +1,73 @@
 use super::figure_type::FigureType;
-use super::matrix::Matrix;
 use super::geometry::Point;
 use super::graphics::Color;
+use super::matrix::Matrix;
+use super::piece_definition::PieceDefinition;
+use super::shape_table::{BlockList, BlockView, Blocks};
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Figure {
     figure_type: FigureType,
-    matrix: Matrix<u8>,
+    definition: Rc<PieceDefinition>,
+    rotations: Rotations,
+    rotation: usize,
+}
+
+// Blocks of every rotation state: the static tables of the built-in figures,
+// computed once when a custom figure is created. Cloning never copies them.
+#[derive(Debug, Clone, PartialEq)]
+enum Rotations {
+    Static(&'static [Blocks; 4]),
+    Computed(Rc<[BlockList]>),
+}
+
+impl Rotations {
+    fn new(figure_type: &FigureType, definition: &PieceDefinition) -> Rotations {
+        if let Some(rotations) = figure_type.static_blocks() {
+            return Rotations::Static(rotations);
+        }
+        let shapes = &definition.rotation_shapes;
+        if !shapes.is_empty() {
+            let rotations = shapes
+                .iter()
+                .map(|shape| BlockList::from_matrix(&Matrix::new(shape.clone())))
+                .collect();
+            return Rotations::Computed(rotations);
+        }
+        let mut matrix = Matrix::new(definition.shape.clone());
+        let mut rotations = vec![];
+        for _ in 0..4 {
+            rotations.push(BlockList::from_matrix(&matrix));
+            matrix = matrix.rotated();
+        }
+        return Rotations::Computed(rotations.into());
+    }
+
+    fn len(&self) -> usize {
+        return match self {
+            Rotations::Static(rotations) => rotations.len(),
+            Rotations::Computed(rotations) => rotations.len(),
+        };
+    }
+
+    fn get(&self, rotation: usize) -> BlockView<'_> {
+        return match self {
+            Rotations::Static(rotations) => rotations[rotation].view(),
+            Rotations::Computed(rotations) => rotations[rotation].view(),
+        };
+    }
 }
 
 impl Figure {
     pub fn new(figure_type: FigureType) -> Figure {
-        let matrix = figure_type.initial_matrix();
+        let definition = figure_type.definition();
+        let rotations = Rotations::new(&figure_type, &definition);
         return Figure {
             figure_type,
-            matrix,
+            definition,
+            rotations,
+            rotation: 0,
         };
     }
 
@@ -23,41 +76,57 @@ impl Figure {
     }
 
     pub fn color(&self) -> Color {
-        return self.figure_type.color();
+        return self.definition.color.clone();
+    }
+
+    // Kick offsets to test when rotating from the given rotation step.
+    pub fn wall_kick_tests(&self, rotation_step: usize) -> &[Point] {
+        return match self.definition.wall_kicks.get(rotation_step) {
+            Some(kicks) => kicks,
+            None => &[],
+        };
+    }
+
+    pub fn rotation_steps(&self) -> usize {
+        return self.definition.rotation_steps();
+    }
+
+    // The wall kick step follows the rotation state, so both can't drift
+    // apart. Figures with fewer steps (like O) repeat them.
+    pub fn rotation_step(&self) -> usize {
+        return self.rotation % self.rotation_steps();
     }
 
-    pub fn wall_kick_tests(&self) -> Vec<Vec<Point>> {
-        return self.figure_type.wall_kick();
+    pub fn spawn_offset(&self) -> Point {
+        return self.definition.spawn_offset;
     }
 
     pub fn rotated(&self) -> Self {
+        let rotation = (self.rotation + 1) % self.rotations.len();
         return Figure {
-            matrix: self.matrix.rotated(),
             figure_type: self.figure_type.clone(),
+            definition: self.definition.clone(),
+            rotations: self.rotations.clone(),
+            rotation,
         };
     }
 
-    pub fn to_cartesian(&self) -> Vec<Point> {
-        let mut points = vec![];
-        for y in 0..self.matrix.height() {
-            for x in 0..self.matrix.width() {
-                if let Some(element) = self.matrix.at_xy(x, y) {
-                    if *element == 1 {
-                        points.push(Point {
-                            x: x as i32,
-                            y: y as i32,
-                        });
-                    }
-                }
-            }
-        }
-        return points;
+    pub fn blocks(&self) -> BlockView<'_> {
+        return self.rotations.get(self.rotation);
     }
 }
 
 #[cfg(test)]
 mod figure_tests {
     use super::*;
+
+    fn cells(shape: Vec<Vec<u8>>) -> Vec<Point> {
+        return BlockList::from_matrix(&Matrix::new(shape))
+            .view()
+            .as_slice()
+            .to_vec();
+    }
+
     #[test]
     fn test_t_figure_rotation() {
         let figure = Figure::new(FigureType::T);
@@ -66,16 +135,18 @@ mod figure_tests {
         let third_rotation = second_rotation.rotated();
         let full_loop_rotation = third_rotation.rotated();
 
-        let first_rotation_matrix = Matrix::new(vec![vec![0, 1, 0], vec![0, 1, 1], vec![0, 1, 0]]);
-        let second_rotation_matrix = Matrix::new(vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 1, 0]]);
-        let third_rotation_matrix = Matrix::new(vec![vec![0, 1, 0], vec![1, 1, 0], vec![0, 1, 0]]);
-        let full_loop_rotation_matrix =
-            Matrix::new(vec![vec![0, 1, 0], vec![1, 1, 1], vec![0, 0, 0]]);
+        let first_rotation_matrix = cells(vec![vec![0, 1, 0], vec![0, 1, 1], vec![0, 1, 0]]);
+        let second_rotation_matrix = cells(vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 1, 0]]);
+        let third_rotation_matrix = cells(vec![vec![0, 1, 0], vec![1, 1, 0], vec![0, 1, 0]]);
+        let full_loop_rotation_matrix = cells(vec![vec![0, 1, 0], vec![1, 1, 1], vec![0, 0, 0]]);
 
-        assert_eq!(first_rotation.matrix, first_rotation_matrix);
-        assert_eq!(second_rotation.matrix, second_rotation_matrix);
-        assert_eq!(third_rotation.matrix, third_rotation_matrix);
-        assert_eq!(full_loop_rotation.matrix, full_loop_rotation_matrix);
+        assert_eq!(first_rotation.blocks().as_slice(), first_rotation_matrix);
+        assert_eq!(second_rotation.blocks().as_slice(), second_rotation_matrix);
+        assert_eq!(third_rotation.blocks().as_slice(), third_rotation_matrix);
+        assert_eq!(
+            full_loop_rotation.blocks().as_slice(),
+            full_loop_rotation_matrix
+        );
     }
     #[test]
     fn test_i_figure_rotation() {
@@ -84,34 +155,37 @@ mod figure_tests {
         let second_rotation = first_rotation.rotated();
         let third_rotation = second_rotation.rotated();
         let full_loop_rotation = third_rotation.rotated();
-        let first_rotation_matrix = Matrix::new(vec![
+        let first_rotation_matrix = cells(vec![
             vec![0, 0, 1, 0],
             vec![0, 0, 1, 0],
             vec![0, 0, 1, 0],
             vec![0, 0, 1, 0],
         ]);
-        let second_rotation_matrix = Matrix::new(vec![
+        let second_rotation_matrix = cells(vec![
             vec![0, 0, 0, 0],
             vec![0, 0, 0, 0],
             vec![1, 1, 1, 1],
             vec![0, 0, 0, 0],
         ]);
-        let third_rotation_matrix = Matrix::new(vec![
+        let third_rotation_matrix = cells(vec![
             vec![0, 1, 0, 0],
             vec![0, 1, 0, 0],
             vec![0, 1, 0, 0],
             vec![0, 1, 0, 0],
         ]);
-        let full_loop_rotation_matrix = Matrix::new(vec![
+        let full_loop_rotation_matrix = cells(vec![
             vec![0, 0, 0, 0],
             vec![1, 1, 1, 1],
             vec![0, 0, 0, 0],
             vec![0, 0, 0, 0],
         ]);
-        assert_eq!(first_rotation.matrix, first_rotation_matrix);
-        assert_eq!(second_rotation.matrix, second_rotation_matrix);
-        assert_eq!(third_rotation.matrix, third_rotation_matrix);
-        assert_eq!(full_loop_rotation.matrix, full_loop_rotation_matrix);
+        assert_eq!(first_rotation.blocks().as_slice(), first_rotation_matrix);
+        assert_eq!(second_rotation.blocks().as_slice(), second_rotation_matrix);
+        assert_eq!(third_rotation.blocks().as_slice(), third_rotation_matrix);
+        assert_eq!(
+            full_loop_rotation.blocks().as_slice(),
+            full_loop_rotation_matrix
+        );
     }
     #[test]
     fn test_l_figure_rotation() {
@@ -121,16 +195,18 @@ mod figure_tests {
         let third_rotation = second_rotation.rotated();
         let full_loop_rotation = third_rotation.rotated();
 
-        let first_rotation_matrix = Matrix::new(vec![vec![0, 1, 0], vec![0, 1, 0], vec![0, 1, 1]]);
-        let second_rotation_matrix = Matrix::new(vec![vec![0, 0, 0], vec![1, 1, 1], vec![1, 0, 0]]);
-        let third_rotation_matrix = Matrix::new(vec![vec![1, 1, 0], vec![0, 1, 0], vec![0, 1, 0]]);
-        let full_loop_rotation_matrix =
-            Matrix::new(vec![vec![0, 0, 1], vec![1, 1, 1], vec![0, 0, 0]]);
+        let first_rotation_matrix = cells(vec![vec![0, 1, 0], vec![0, 1, 0], vec![0, 1, 1]]);
+        let second_rotation_matrix = cells(vec![vec![0, 0, 0], vec![1, 1, 1], vec![1, 0, 0]]);
+        let third_rotation_matrix = cells(vec![vec![1, 1, 0], vec![0, 1, 0], vec![0, 1, 0]]);
+        let full_loop_rotation_matrix = cells(vec![vec![0, 0, 1], vec![1, 1, 1], vec![0, 0, 0]]);
 
-        assert_eq!(first_rotation.matrix, first_rotation_matrix);
-        assert_eq!(second_rotation.matrix, second_rotation_matrix);
-        assert_eq!(third_rotation.matrix, third_rotation_matrix);
-        assert_eq!(full_loop_rotation.matrix, full_loop_rotation_matrix);
+        assert_eq!(first_rotation.blocks().as_slice(), first_rotation_matrix);
+        assert_eq!(second_rotation.blocks().as_slice(), second_rotation_matrix);
+        assert_eq!(third_rotation.blocks().as_slice(), third_rotation_matrix);
+        assert_eq!(
+            full_loop_rotation.blocks().as_slice(),
+            full_loop_rotation_matrix
+        );
     }
     #[test]
     fn test_j_figure_rotation() {
@@ -140,16 +216,18 @@ mod figure_tests {
         let third_rotation = second_rotation.rotated();
         let full_loop_rotation = third_rotation.rotated();
 
-        let first_rotation_matrix = Matrix::new(vec![vec![0, 1, 1], vec![0, 1, 0], vec![0, 1, 0]]);
-        let second_rotation_matrix = Matrix::new(vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 0, 1]]);
-        let third_rotation_matrix = Matrix::new(vec![vec![0, 1, 0], vec![0, 1, 0], vec![1, 1, 0]]);
-        let full_loop_rotation_matrix =
-            Matrix::new(vec![vec![1, 0, 0], vec![1, 1, 1], vec![0, 0, 0]]);
+        let first_rotation_matrix = cells(vec![vec![0, 1, 1], vec![0, 1, 0], vec![0, 1, 0]]);
+        let second_rotation_matrix = cells(vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 0, 1]]);
+        let third_rotation_matrix = cells(vec![vec![0, 1, 0], vec![0, 1, 0], vec![1, 1, 0]]);
+        let full_loop_rotation_matrix = cells(vec![vec![1, 0, 0], vec![1, 1, 1], vec![0, 0, 0]]);
 
-        assert_eq!(first_rotation.matrix, first_rotation_matrix);
-        assert_eq!(second_rotation.matrix, second_rotation_matrix);
-        assert_eq!(third_rotation.matrix, third_rotation_matrix);
-        assert_eq!(full_loop_rotation.matrix, full_loop_rotation_matrix);
+        assert_eq!(first_rotation.blocks().as_slice(), first_rotation_matrix);
+        assert_eq!(second_rotation.blocks().as_slice(), second_rotation_matrix);
+        assert_eq!(third_rotation.blocks().as_slice(), third_rotation_matrix);
+        assert_eq!(
+            full_loop_rotation.blocks().as_slice(),
+            full_loop_rotation_matrix
+        );
     }
     #[test]
     fn test_s_figure_rotation() {
@@ -159,16 +237,18 @@ mod figure_tests {
         let third_rotation = second_rotation.rotated();
         let full_loop_rotation = third_rotation.rotated();
 
-        let first_rotation_matrix = Matrix::new(vec![vec![0, 1, 0], vec![0, 1, 1], vec![0, 0, 1]]);
-        let second_rotation_matrix = Matrix::new(vec![vec![0, 0, 0], vec![0, 1, 1], vec![1, 1, 0]]);
-        let third_rotation_matrix = Matrix::new(vec![vec![1, 0, 0], vec![1, 1, 0], vec![0, 1, 0]]);
-        let full_loop_rotation_matrix =
-            Matrix::new(vec![vec![0, 1, 1], vec![1, 1, 0], vec![0, 0, 0]]);
+        let first_rotation_matrix = cells(vec![vec![0, 1, 0], vec![0, 1, 1], vec![0, 0, 1]]);
+        let second_rotation_matrix = cells(vec![vec![0, 0, 0], vec![0, 1, 1], vec![1, 1, 0]]);
+        let third_rotation_matrix = cells(vec![vec![1, 0, 0], vec![1, 1, 0], vec![0, 1, 0]]);
+        let full_loop_rotation_matrix = cells(vec![vec![0, 1, 1], vec![1, 1, 0], vec![0, 0, 0]]);
 
-        assert_eq!(first_rotation.matrix, first_rotation_matrix);
-        assert_eq!(second_rotation.matrix, second_rotation_matrix);
-        assert_eq!(third_rotation.matrix, third_rotation_matrix);
-        assert_eq!(full_loop_rotation.matrix, full_loop_rotation_matrix);
+        assert_eq!(first_rotation.blocks().as_slice(), first_rotation_matrix);
+        assert_eq!(second_rotation.blocks().as_slice(), second_rotation_matrix);
+        assert_eq!(third_rotation.blocks().as_slice(), third_rotation_matrix);
+        assert_eq!(
+            full_loop_rotation.blocks().as_slice(),
+            full_loop_rotation_matrix
+        );
     }
     #[test]
     fn test_z_figure_rotation() {
@@ -178,16 +258,18 @@ mod figure_tests {
         let third_rotation = second_rotation.rotated();
         let full_loop_rotation = third_rotation.rotated();
 
-        let first_rotation_matrix = Matrix::new(vec![vec![0, 0, 1], vec![0, 1, 1], vec![0, 1, 0]]);
-        let second_rotation_matrix = Matrix::new(vec![vec![0, 0, 0], vec![1, 1, 0], vec![0, 1, 1]]);
-        let third_rotation_matrix = Matrix::new(vec![vec![0, 1, 0], vec![1, 1, 0], vec![1, 0, 0]]);
-        let full_loop_rotation_matrix =
-            Matrix::new(vec![vec![1, 1, 0], vec![0, 1, 1], vec![0, 0, 0]]);
+        let first_rotation_matrix = cells(vec![vec![0, 0, 1], vec![0, 1, 1], vec![0, 1, 0]]);
+        let second_rotation_matrix = cells(vec![vec![0, 0, 0], vec![1, 1, 0], vec![0, 1, 1]]);
+        let third_rotation_matrix = cells(vec![vec![0, 1, 0], vec![1, 1, 0], vec![1, 0, 0]]);
+        let full_loop_rotation_matrix = cells(vec![vec![1, 1, 0], vec![0, 1, 1], vec![0, 0, 0]]);
 
-        assert_eq!(first_rotation.matrix, first_rotation_matrix);
-        assert_eq!(second_rotation.matrix, second_rotation_matrix);
-        assert_eq!(third_rotation.matrix, third_rotation_matrix);
-        assert_eq!(full_loop_rotation.matrix, full_loop_rotation_matrix);
+        assert_eq!(first_rotation.blocks().as_slice(), first_rotation_matrix);
+        assert_eq!(second_rotation.blocks().as_slice(), second_rotation_matrix);
+        assert_eq!(third_rotation.blocks().as_slice(), third_rotation_matrix);
+        assert_eq!(
+            full_loop_rotation.blocks().as_slice(),
+            full_loop_rotation_matrix
+        );
     }
     #[test]
     fn test_o_figure_rotation() {
@@ -197,17 +279,29 @@ mod figure_tests {
         let third_rotation = second_rotation.rotated();
         let full_loop_rotation = third_rotation.rotated();
 
-        let how_it_should_always_look_like = Matrix::new(vec![vec![1, 1], vec![1, 1]]);
+        let how_it_should_always_look_like = cells(vec![vec![1, 1], vec![1, 1]]);
 
-        assert_eq!(first_rotation.matrix, how_it_should_always_look_like);
-        assert_eq!(second_rotation.matrix, how_it_should_always_look_like);
-        assert_eq!(third_rotation.matrix, how_it_should_always_look_like);
-        assert_eq!(full_loop_rotation.matrix, how_it_should_always_look_like);
+        assert_eq!(
+            first_rotation.blocks().as_slice(),
+            how_it_should_always_look_like
+        );
+        assert_eq!(
+            second_rotation.blocks().as_slice(),
+            how_it_should_always_look_like
+        );
+        assert_eq!(
+            third_rotation.blocks().as_slice(),
+            how_it_should_always_look_like
+        );
+        assert_eq!(
+            full_loop_rotation.blocks().as_slice(),
+            how_it_should_always_look_like
+        );
     }
     #[test]
     fn test_draw() {
         let figure = Figure::new(FigureType::T);
-        let drawed = figure.to_cartesian();
+        let drawed = figure.blocks().as_slice();
         assert_eq!(drawed.len(), 4);
         assert_eq!(drawed[0], Point { x: 1, y: 0 });
         assert_eq!(drawed[1], Point { x: 0, y: 1 });
@@ -215,6 +309,86 @@ mod figure_tests {
         assert_eq!(drawed[3], Point { x: 2, y: 1 });
     }
     #[test]
+    fn test_custom_figure() {
+        let definition = PieceDefinition::new(
+            "I3",
+            vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 0, 0]],
+            FigureType::I.color(),
+        );
+        let figure = Figure::new(FigureType::Custom(Rc::new(definition)));
+        assert_eq!(figure.color(), FigureType::I.color());
+        assert_eq!(figure.rotation_steps(), 4);
+        assert_eq!(
+            figure.rotated().blocks().as_slice(),
+            vec![
+                Point { x: 1, y: 0 },
+                Point { x: 1, y: 1 },
+                Point { x: 1, y: 2 }
+            ]
+        );
+    }
+    #[test]
+    fn test_custom_rotation_shapes() {
+        let definition = PieceDefinition {
+            wall_kicks: vec![vec![], vec![]],
+            rotation_shapes: vec![vec![vec![1, 1]], vec![vec![1], vec![1]]],
+            ..PieceDefinition::new("bar", vec![vec![1, 1]], FigureType::I.color())
+        };
+        let figure = Figure::new(FigureType::Custom(Rc::new(definition)));
+        let rotated = figure.rotated();
+        assert_eq!(
+            rotated.blocks().as_slice(),
+            vec![Point { x: 0, y: 0 }, Point { x: 0, y: 1 }]
+        );
+        assert_eq!(
+            rotated.rotated().blocks().as_slice(),
+            figure.blocks().as_slice()
+        );
+        assert!(rotated.wall_kick_tests(1).is_empty());
+        assert!(rotated.wall_kick_tests(2).is_empty());
+    }
+    #[test]
+    fn test_cloning_shares_rotations() {
+        let shape = vec![vec![1, 0], vec![1, 1]];
+        let definition = PieceDefinition::new("V", shape, FigureType::I.color());
+        let figure = Figure::new(FigureType::Custom(Rc::new(definition)));
+        let rotated = figure.rotated().rotated();
+        match (&figure.rotations, &rotated.rotations) {
+            (Rotations::Computed(first), Rotations::Computed(second)) => {
+                assert!(Rc::ptr_eq(first, second));
+            }
+            _ => panic!("custom figures compute their rotations"),
+        }
+        assert_eq!(rotated.blocks().max(), Point { x: 1, y: 1 });
+    }
+    #[test]
+    fn test_built_in_figures_share_definitions() {
+        let figure = Figure::new(FigureType::T);
+        let other = Figure::new(FigureType::T);
+        assert!(Rc::ptr_eq(&figure.definition, &other.definition));
+        assert_eq!(figure.definition.name, "T");
+    }
+    #[test]
+    fn test_rotation_step_follows_rotation() {
+        let definition = PieceDefinition {
+            wall_kicks: vec![vec![]; 3],
+            ..PieceDefinition::new("V", vec![vec![1, 0], vec![1, 1]], FigureType::I.color())
+        };
+        let figure = Figure::new(FigureType::Custom(Rc::new(definition)));
+        let full_turn = (0..4).fold(figure.clone(), |figure, _| figure.rotated());
+        assert_eq!(full_turn.blocks(), figure.blocks());
+        assert_eq!(full_turn.rotation_step(), 0);
+        assert_eq!(Figure::new(FigureType::O).rotated().rotation_step(), 0);
+    }
+    #[test]
+    fn test_big_custom_figure() {
+        let shape = vec![vec![1; 5]; 5];
+        let definition = PieceDefinition::new("big", shape, FigureType::I.color());
+        let figure = Figure::new(FigureType::Custom(Rc::new(definition)));
+        assert_eq!(figure.blocks().as_slice().len(), 25);
+        assert_eq!(figure.rotated().blocks().max(), Point { x: 4, y: 4 });
+    }
+    #[test]
     fn test_to_cartesian() {
         let figure = Figure::new(FigureType::O);
         let expectation = vec![
@@ -223,7 +397,7 @@ mod figure_tests {
             Point { x: 0, y: 1 },
             Point { x: 1, y: 1 },
         ];
-        let cartesian = figure.to_cartesian();
+        let cartesian = figure.blocks().as_slice();
         assert_eq!(cartesian, expectation);
     }
 }
@@ -1,8 +1,14 @@
 // Based on https://tetris.fandom.com/wiki/SRS
 
-use super::matrix::Matrix;
 use super::geometry::Point;
 use super::graphics::Color;
+use super::matrix::Matrix;
+use super::piece_definition::PieceDefinition;
+use super::shape_table::{
+    shape_to_vec, Blocks, GARBAGE_BLOCKS, GARBAGE_SHAPE, I_BLOCKS, I_SHAPE, J_BLOCKS, J_SHAPE,
+    L_BLOCKS, L_SHAPE, O_BLOCKS, O_SHAPE, S_BLOCKS, S_SHAPE, T_BLOCKS, T_SHAPE, Z_BLOCKS, Z_SHAPE,
+};
+use std::rc::Rc;
 
 const I_COLOR: Color = Color {
     red: 108.0 / 255.0,
@@ -53,6 +59,33 @@ const S_COLOR: Color = Color {
     alpha: 1.0,
 };
 
+const GARBAGE_COLOR: Color = Color {
+    red: 128.0 / 255.0,
+    green: 128.0 / 255.0,
+    blue: 128.0 / 255.0,
+    alpha: 1.0,
+};
+
+// In the order of the indexes used by `FigureType::definition`.
+const BUILT_IN_TYPES: [FigureType; 8] = [
+    FigureType::I,
+    FigureType::T,
+    FigureType::L,
+    FigureType::J,
+    FigureType::O,
+    FigureType::Z,
+    FigureType::S,
+    FigureType::Garbage,
+];
+
+thread_local! {
+    // Built once, so creating a built-in figure only clones an Rc.
+    static BUILT_IN_DEFINITIONS: Vec<Rc<PieceDefinition>> = BUILT_IN_TYPES
+        .iter()
+        .map(|figure_type| Rc::new(figure_type.built_in_definition()))
+        .collect();
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum FigureType {
     I,
@@ -62,6 +95,8 @@ pub enum FigureType {
     O,
     Z,
     S,
+    Garbage,
+    Custom(Rc<PieceDefinition>),
 }
 
 impl FigureType {
@@ -74,87 +109,95 @@ impl FigureType {
             FigureType::S => S_COLOR,
             FigureType::T => T_COLOR,
             FigureType::Z => Z_COLOR,
+            FigureType::Garbage => GARBAGE_COLOR,
+            FigureType::Custom(definition) => definition.color.clone(),
         };
     }
 
-    pub fn initial_matrix(&self) -> Matrix<u8> {
-        let vectors = match self {
-            FigureType::I => self.draw_i(),
-            FigureType::J => self.draw_j(),
-            FigureType::L => self.draw_l(),
-            FigureType::O => self.draw_o(),
-            FigureType::S => self.draw_s(),
-            FigureType::T => self.draw_t(),
-            FigureType::Z => self.draw_z(),
+    pub fn definition(&self) -> Rc<PieceDefinition> {
+        let index = match self {
+            FigureType::I => 0,
+            FigureType::T => 1,
+            FigureType::L => 2,
+            FigureType::J => 3,
+            FigureType::O => 4,
+            FigureType::Z => 5,
+            FigureType::S => 6,
+            FigureType::Garbage => 7,
+            FigureType::Custom(definition) => return definition.clone(),
         };
-        return Matrix::new(vectors);
+        return BUILT_IN_DEFINITIONS.with(|definitions| definitions[index].clone());
     }
 
-    pub fn wall_kick(&self) -> Vec<Vec<Point>> {
-        return match self {
-            FigureType::O => vec![vec![]],
-            FigureType::I => FigureType::wall_kick_i(),
-            _ => FigureType::wall_kick_default(),
+    fn built_in_definition(&self) -> PieceDefinition {
+        return PieceDefinition {
+            name: self.name(),
+            shape: self.shape(),
+            color: self.color(),
+            wall_kicks: self.wall_kick(),
+            spawn_offset: Point { x: 0, y: 0 },
+            rotation_shapes: vec![],
         };
     }
 
-    fn draw_i(&self) -> Vec<Vec<u8>> {
-        return vec![
-            vec![0, 0, 0, 0], //
-            vec![1, 1, 1, 1],
-            vec![0, 0, 0, 0],
-            vec![0, 0, 0, 0],
-        ];
-    }
-
-    fn draw_j(&self) -> Vec<Vec<u8>> {
-        return vec![
-            vec![1, 0, 0], //
-            vec![1, 1, 1],
-            vec![0, 0, 0],
-        ];
-    }
-
-    fn draw_l(&self) -> Vec<Vec<u8>> {
-        return vec![
-            vec![0, 0, 1], //
-            vec![1, 1, 1],
-            vec![0, 0, 0],
-        ];
+    pub fn name(&self) -> String {
+        let name = match self {
+            FigureType::I => "I",
+            FigureType::J => "J",
+            FigureType::L => "L",
+            FigureType::O => "O",
+            FigureType::S => "S",
+            FigureType::T => "T",
+            FigureType::Z => "Z",
+            FigureType::Garbage => "Garbage",
+            FigureType::Custom(definition) => &definition.name,
+        };
+        return name.to_string();
     }
 
-    fn draw_o(&self) -> Vec<Vec<u8>> {
-        return vec![
-            vec![1, 1], //
-            vec![1, 1],
-        ];
+    pub fn initial_matrix(&self) -> Matrix<u8> {
+        return Matrix::new(self.shape());
     }
 
-    fn draw_s(&self) -> Vec<Vec<u8>> {
-        return vec![
-            vec![0, 1, 1], //
-            vec![1, 1, 0],
-            vec![0, 0, 0],
-        ];
+    fn shape(&self) -> Vec<Vec<u8>> {
+        return match self {
+            FigureType::I => shape_to_vec(I_SHAPE),
+            FigureType::J => shape_to_vec(J_SHAPE),
+            FigureType::L => shape_to_vec(L_SHAPE),
+            FigureType::O => shape_to_vec(O_SHAPE),
+            FigureType::S => shape_to_vec(S_SHAPE),
+            FigureType::T => shape_to_vec(T_SHAPE),
+            FigureType::Z => shape_to_vec(Z_SHAPE),
+            FigureType::Garbage => shape_to_vec(GARBAGE_SHAPE),
+            FigureType::Custom(definition) => definition.shape.clone(),
+        };
     }
 
-    fn draw_t(&self) -> Vec<Vec<u8>> {
-        return vec![
-            vec![0, 1, 0], //
-            vec![1, 1, 1],
-            vec![0, 0, 0],
-        ];
+    // Blocks of every rotation state, precomputed for the built-in figures.
+    pub(crate) fn static_blocks(&self) -> Option<&'static [Blocks; 4]> {
+        return match self {
+            FigureType::I => Some(&I_BLOCKS),
+            FigureType::J => Some(&J_BLOCKS),
+            FigureType::L => Some(&L_BLOCKS),
+            FigureType::O => Some(&O_BLOCKS),
+            FigureType::S => Some(&S_BLOCKS),
+            FigureType::T => Some(&T_BLOCKS),
+            FigureType::Z => Some(&Z_BLOCKS),
+            FigureType::Garbage => Some(&GARBAGE_BLOCKS),
+            FigureType::Custom(_) => None,
+        };
     }
 
-    fn draw_z(&self) -> Vec<Vec<u8>> {
-        return vec![
-            vec![1, 1, 0], //
-            vec![0, 1, 1],
-            vec![0, 0, 0],
-        ];
+    pub fn wall_kick(&self) -> Vec<Vec<Point>> {
+        return match self {
+            FigureType::O | FigureType::Garbage => vec![vec![]],
+            FigureType::I => FigureType::wall_kick_i(),
+            FigureType::Custom(definition) => definition.wall_kicks.clone(),
+            _ => FigureType::wall_kick_default(),
+        };
     }
 
-    fn wall_kick_default() -> Vec<Vec<Point>> {
+    pub(crate) fn wall_kick_default() -> Vec<Vec<Point>> {
         return vec![
             vec![
                 Point { x: 0, y: 0 },
@@ -187,7 +230,7 @@ impl FigureType {
         ];
     }
 
-    fn wall_kick_i() -> Vec<Vec<Point>> {
+    pub(crate) fn wall_kick_i() -> Vec<Vec<Point>> {
         return vec![
             vec![
                 Point { x: 0, y: 0 },
@@ -48,19 +48,9 @@ impl<T> Matrix<T> {
     where
         T: Clone,
     {
-        let mut data = vec![];
-        for _y in 0..self.height() {
-            let mut vec = vec![];
-            for _x in 0..self.width() {
-                if _x == x && _y == y {
-                    vec.push(element.clone());
-                } else {
-                    vec.push(self.data[_y][_x].clone());
-                }
-            }
-            data.push(vec);
-        }
-        return Matrix { data };
+        let mut matrix = self.clone();
+        matrix.set(x, y, element);
+        return matrix;
     }
 
     pub fn row_at(&self, line: usize) -> Option<&Vec<T>> {
@@ -70,4 +60,112 @@ impl<T> Matrix<T> {
             return Some(&self.data[line]);
         }
     }
+
+    // IN PLACE CHANGES
+
+    // Writes an element, ignoring positions outside the matrix.
+    pub fn set(&mut self, x: usize, y: usize, element: T) {
+        if x >= self.width() || y >= self.height() {
+            return;
+        }
+        self.data[y][x] = element;
+    }
+
+    // Removes the rows moving the rows above them down. The rows left at the
+    // top are reused and filled with default elements.
+    pub fn clear_rows(&mut self, lines: &[usize])
+    where
+        T: Default,
+    {
+        let mut target = self.height();
+        for source in (0..self.height()).rev() {
+            if lines.contains(&source) {
+                continue;
+            }
+            target -= 1;
+            self.data.swap(source, target);
+        }
+        for row in &mut self.data[..target] {
+            row.iter_mut().for_each(|element| *element = T::default());
+        }
+    }
+
+    // Pushes the rows up adding the given rows at the bottom, the last one
+    // lowest. Rows pushed beyond the top are discarded.
+    pub fn insert_rows_bottom(&mut self, rows: &[Vec<T>])
+    where
+        T: Clone + Default,
+    {
+        let count = rows.len().min(self.height());
+        self.shift_up(count);
+        let first_line = self.height() - count;
+        for (line, row) in self.data[first_line..].iter_mut().zip(rows) {
+            line.clone_from_slice(row);
+        }
+    }
+
+    // Moves every row up, leaving rows of default elements at the bottom.
+    pub fn shift_up(&mut self, count: usize)
+    where
+        T: Default,
+    {
+        let count = count.min(self.height());
+        self.data.rotate_left(count);
+        let first_line = self.height() - count;
+        for row in &mut self.data[first_line..] {
+            row.iter_mut().for_each(|element| *element = T::default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod matrix_tests {
+    use super::*;
+
+    fn get_matrix() -> Matrix<u8> {
+        return Matrix::new(vec![vec![1, 0], vec![2, 2], vec![3, 0]]);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut matrix = get_matrix();
+        matrix.set(1, 0, 5);
+        matrix.set(2, 0, 5);
+        assert_eq!(matrix, get_matrix().replacing_at_xy(1, 0, 5));
+        assert_eq!(matrix.at_xy(1, 0), Some(&5));
+    }
+    #[test]
+    fn test_clear_rows() {
+        let mut matrix = get_matrix();
+        matrix.clear_rows(&[1]);
+        assert_eq!(
+            matrix,
+            Matrix::new(vec![vec![0, 0], vec![1, 0], vec![3, 0]])
+        );
+        matrix.clear_rows(&[0, 2]);
+        assert_eq!(
+            matrix,
+            Matrix::new(vec![vec![0, 0], vec![0, 0], vec![1, 0]])
+        );
+    }
+    #[test]
+    fn test_insert_rows_bottom() {
+        let mut matrix = get_matrix();
+        matrix.insert_rows_bottom(&[vec![4, 4], vec![5, 0]]);
+        assert_eq!(
+            matrix,
+            Matrix::new(vec![vec![3, 0], vec![4, 4], vec![5, 0]])
+        );
+    }
+    #[test]
+    fn test_shift_up() {
+        let mut matrix = get_matrix();
+        matrix.shift_up(1);
+        assert_eq!(
+            matrix,
+            Matrix::new(vec![vec![2, 2], vec![3, 0], vec![0, 0]])
+        );
+        matrix.shift_up(5);
+        assert_eq!(matrix, Matrix::new(vec![vec![0, 0]; 3]));
+    }
 }
@@ -1,9 +1,15 @@
+#[allow(clippy::module_inception)]
 mod figure;
 mod figure_type;
 mod matrix;
+mod piece_definition;
+mod piece_parser;
+mod shape_table;
 mod utilities;
-pub use utilities::{block, geometry, graphics};
 pub use figure::Figure;
 pub use figure_type::FigureType;
-pub use matrix::Matrix;
+pub use piece_definition::{PieceDefinition, PieceSet};
+pub use piece_parser::PieceParseError;
+#[allow(unused_imports)]
 pub use utilities::block::Block;
+pub use utilities::{block, geometry, graphics};
@@ -0,0 +1,251 @@
+use super::figure_type::FigureType;
+use super::geometry::Point;
+use super::graphics::Color;
+use super::piece_parser::{parse_pieces, PieceParseError};
+use std::rc::Rc;
+
+// Everything needed to play a figure: its shape in spawn orientation, its color,
+// the wall kick tests for every rotation step and where it spawns relative to
+// the board start point. Shapes are square so they can be rotated, unless
+// `rotation_shapes` lists the shape of every rotation state.
+// A kick table with a single step makes the figure keep its rotation step (like O).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PieceDefinition {
+    pub name: String,
+    pub shape: Vec<Vec<u8>>,
+    pub color: Color,
+    pub wall_kicks: Vec<Vec<Point>>,
+    pub spawn_offset: Point,
+    pub rotation_shapes: Vec<Vec<Vec<u8>>>,
+}
+
+impl PieceDefinition {
+    // Uses the SRS kicks of the J, L, S, T and Z figures and no spawn offset.
+    pub fn new(name: &str, shape: Vec<Vec<u8>>, color: Color) -> PieceDefinition {
+        return PieceDefinition {
+            name: name.to_string(),
+            shape,
+            color,
+            wall_kicks: FigureType::wall_kick_default(),
+            spawn_offset: Point { x: 0, y: 0 },
+            rotation_shapes: vec![],
+        };
+    }
+
+    pub fn rotation_steps(&self) -> usize {
+        return self.wall_kicks.len().max(1);
+    }
+
+    pub fn blocks_count(&self) -> usize {
+        return self
+            .shape
+            .iter()
+            .flatten()
+            .filter(|cell| **cell == 1)
+            .count();
+    }
+}
+
+// The figures a game deals from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PieceSet {
+    figures: Vec<FigureType>,
+}
+
+impl PieceSet {
+    pub fn new(definitions: Vec<PieceDefinition>) -> PieceSet {
+        let figures = definitions
+            .into_iter()
+            .map(|definition| FigureType::Custom(Rc::new(definition)))
+            .collect();
+        return PieceSet { figures };
+    }
+
+    // Loads the pieces described with the text format in `piece_parser`.
+    pub fn parse(text: &str) -> Result<PieceSet, PieceParseError> {
+        return Ok(PieceSet::new(parse_pieces(text)?));
+    }
+
+    pub fn tetrominoes() -> PieceSet {
+        return PieceSet {
+            figures: vec![
+                FigureType::I,
+                FigureType::J,
+                FigureType::L,
+                FigureType::O,
+                FigureType::S,
+                FigureType::T,
+                FigureType::Z,
+            ],
+        };
+    }
+
+    pub fn trominoes() -> PieceSet {
+        let straight = PieceDefinition::new(
+            "I3",
+            vec![vec![0, 0, 0], vec![1, 1, 1], vec![0, 0, 0]],
+            rgb(108, 237, 238),
+        );
+        let corner = PieceDefinition::new("L3", vec![vec![1, 0], vec![1, 1]], rgb(229, 162, 67));
+        return PieceSet::new(vec![straight, corner]);
+    }
+
+    // The 18 one-sided pentominoes.
+    pub fn pentominoes() -> PieceSet {
+        let mut straight = PieceDefinition::new(
+            "I5",
+            vec![
+                vec![0, 0, 0, 0, 0],
+                vec![0, 0, 0, 0, 0],
+                vec![1, 1, 1, 1, 1],
+                vec![0, 0, 0, 0, 0],
+                vec![0, 0, 0, 0, 0],
+            ],
+            rgb(108, 237, 238),
+        );
+        straight.wall_kicks = FigureType::wall_kick_i();
+        straight.spawn_offset = Point { x: 0, y: -1 };
+        let long = |name: &str, first_row: Vec<u8>, second_row: Vec<u8>, color: Color| {
+            let mut definition = PieceDefinition::new(
+                name,
+                vec![first_row, second_row, vec![0; 4], vec![0; 4]],
+                color,
+            );
+            definition.wall_kicks = FigureType::wall_kick_i();
+            return definition;
+        };
+        return PieceSet::new(vec![
+            straight,
+            long("L5", vec![0, 0, 0, 1], vec![1, 1, 1, 1], rgb(229, 162, 67)),
+            long("J5", vec![1, 0, 0, 0], vec![1, 1, 1, 1], rgb(0, 33, 230)),
+            long("N", vec![1, 1, 0, 0], vec![0, 1, 1, 1], rgb(221, 47, 23)),
+            long("N'", vec![0, 0, 1, 1], vec![1, 1, 1, 0], rgb(110, 235, 71)),
+            long("Y", vec![0, 0, 1, 0], vec![1, 1, 1, 1], rgb(146, 45, 231)),
+            long("Y'", vec![0, 1, 0, 0], vec![1, 1, 1, 1], rgb(200, 90, 200)),
+            PieceDefinition::new(
+                "F",
+                vec![vec![0, 1, 1], vec![1, 1, 0], vec![0, 1, 0]],
+                rgb(240, 128, 128),
+            ),
+            PieceDefinition::new(
+                "F'",
+                vec![vec![1, 1, 0], vec![0, 1, 1], vec![0, 1, 0]],
+                rgb(128, 160, 240),
+            ),
+            PieceDefinition::new(
+                "P",
+                vec![vec![1, 1, 0], vec![1, 1, 1], vec![0, 0, 0]],
+                rgb(241, 238, 79),
+            ),
+            PieceDefinition::new(
+                "P'",
+                vec![vec![0, 1, 1], vec![1, 1, 1], vec![0, 0, 0]],
+                rgb(250, 200, 60),
+            ),
+            PieceDefinition::new(
+                "T5",
+                vec![vec![1, 1, 1], vec![0, 1, 0], vec![0, 1, 0]],
+                rgb(170, 90, 240),
+            ),
+            PieceDefinition::new(
+                "U",
+                vec![vec![1, 0, 1], vec![1, 1, 1], vec![0, 0, 0]],
+                rgb(60, 180, 160),
+            ),
+            PieceDefinition::new(
+                "V",
+                vec![vec![1, 0, 0], vec![1, 0, 0], vec![1, 1, 1]],
+                rgb(90, 120, 200),
+            ),
+            PieceDefinition::new(
+                "W",
+                vec![vec![1, 0, 0], vec![1, 1, 0], vec![0, 1, 1]],
+                rgb(180, 220, 90),
+            ),
+            PieceDefinition::new(
+                "X",
+                vec![vec![0, 1, 0], vec![1, 1, 1], vec![0, 1, 0]],
+                rgb(230, 230, 230),
+            ),
+            PieceDefinition::new(
+                "Z5",
+                vec![vec![1, 1, 0], vec![0, 1, 0], vec![0, 1, 1]],
+                rgb(200, 60, 60),
+            ),
+            PieceDefinition::new(
+                "S5",
+                vec![vec![0, 1, 1], vec![0, 1, 0], vec![1, 1, 0]],
+                rgb(60, 200, 60),
+            ),
+        ]);
+    }
+
+    pub fn get_figures(&self) -> &[FigureType] {
+        return &self.figures;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.figures.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.figures.is_empty();
+    }
+}
+
+fn rgb(red: u8, green: u8, blue: u8) -> Color {
+    return Color {
+        red: red as f32 / 255.0,
+        green: green as f32 / 255.0,
+        blue: blue as f32 / 255.0,
+        alpha: 1.0,
+    };
+}
+
+#[cfg(test)]
+mod piece_definition_tests {
+    use super::*;
+
+    #[test]
+    fn test_pentominoes_have_five_blocks() {
+        let set = PieceSet::pentominoes();
+        assert_eq!(set.len(), 18);
+        for figure in set.get_figures() {
+            assert_eq!(figure.definition().blocks_count(), 5);
+        }
+    }
+    #[test]
+    fn test_trominoes_have_three_blocks() {
+        let set = PieceSet::trominoes();
+        assert_eq!(set.len(), 2);
+        for figure in set.get_figures() {
+            assert_eq!(figure.definition().blocks_count(), 3);
+        }
+    }
+    #[test]
+    fn test_pentominoes_are_different() {
+        let set = PieceSet::pentominoes();
+        let figures = set.get_figures();
+        for (index, figure) in figures.iter().enumerate() {
+            assert!(!figures[index + 1..].contains(figure));
+        }
+    }
+    #[test]
+    fn test_parse_piece_set() {
+        let set = PieceSet::parse("piece dot\ncolor 0 0 0\nshape\nX\nend").unwrap();
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.get_figures()[0].name(), "dot");
+        assert!(PieceSet::parse("piece dot").is_err());
+    }
+    #[test]
+    fn test_custom_definition_defaults() {
+        let definition = PieceDefinition::new("dot", vec![vec![1]], rgb(0, 0, 0));
+        assert_eq!(definition.rotation_steps(), 4);
+        assert_eq!(definition.spawn_offset, Point { x: 0, y: 0 });
+        let fixed = PieceDefinition {
+            wall_kicks: vec![vec![]],
+            ..definition
+        };
+        assert_eq!(fixed.rotation_steps(), 1);
+    }
+}
@@ -0,0 +1,472 @@
+// Text format for piece sets. Every piece is a block like:
+//
+//   piece T
+//   color 146 45 231
+//   spawn 0 0
+//   kicks 0,0 -1,0 -1,1 0,-2 -1,-2
+//   kicks 0,0 1,0 -1,1 0,2 1,2
+//   kicks 0,0 1,0 1,1 0,-2 1,-2
+//   kicks 0,0 -1,0 -1,-1 0,2 -1,2
+//   shape
+//   .X.
+//   XXX
+//   ...
+//   end
+//
+// `color` is required, `spawn` defaults to 0 0 and `kicks` to the SRS kicks
+// of J, L, S, T and Z (`kicks none` keeps the rotation step, like O).
+// One `shape` grid is rotated to get the other rotation states (so it needs
+// four `kicks` lines), or a grid can be given for every rotation state (and
+// one `kicks` line for each). Lines starting with `#` are comments.
+
+use super::geometry::Point;
+use super::graphics::Color;
+use super::piece_definition::PieceDefinition;
+use std::error::Error;
+use std::fmt;
+
+// Rotation states of a single rotated shape.
+const ROTATIONS: usize = 4;
+
+// Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PieceParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PieceParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        return write!(
+            formatter,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        );
+    }
+}
+
+impl Error for PieceParseError {}
+
+struct Token<'a> {
+    text: &'a str,
+    column: usize,
+}
+
+struct PieceBuilder {
+    name: String,
+    line: usize,
+    color: Option<Color>,
+    spawn_offset: Point,
+    wall_kicks: Vec<Vec<Point>>,
+    fixed_rotation: bool,
+    shapes: Vec<Vec<Vec<u8>>>,
+    shape_lines: Vec<usize>,
+    reading_shape: bool,
+}
+
+pub fn parse_pieces(text: &str) -> Result<Vec<PieceDefinition>, PieceParseError> {
+    let mut definitions = vec![];
+    let mut piece: Option<PieceBuilder> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let tokens = tokenize(line);
+        let first = match tokens.first() {
+            Some(token) if !token.text.starts_with('#') => token,
+            _ => continue,
+        };
+        let current = match piece.as_mut() {
+            Some(current) => current,
+            None => {
+                if first.text != "piece" {
+                    return Err(error(line_number, first.column, "expected `piece`"));
+                }
+                let name = expect_single(&tokens, line_number, "a piece name")?;
+                piece = Some(PieceBuilder::new(name.text, line_number));
+                continue;
+            }
+        };
+        if current.reading_shape && is_grid_line(first.text) {
+            if tokens.len() > 1 {
+                return Err(error(
+                    line_number,
+                    tokens[1].column,
+                    "unexpected text after grid",
+                ));
+            }
+            current.push_grid_line(first.text);
+            continue;
+        }
+        current.reading_shape = false;
+        match first.text {
+            "color" => current.color = Some(parse_color(&tokens, line_number)?),
+            "spawn" => current.spawn_offset = parse_spawn(&tokens, line_number)?,
+            "kicks" => parse_kicks(current, &tokens, line_number)?,
+            "shape" => {
+                if tokens.len() > 1 {
+                    return Err(error(
+                        line_number,
+                        tokens[1].column,
+                        "unexpected text after `shape`",
+                    ));
+                }
+                current.shapes.push(vec![]);
+                current.shape_lines.push(line_number);
+                current.reading_shape = true;
+            }
+            "end" => {
+                definitions.push(current.build(line_number, first.column)?);
+                piece = None;
+            }
+            "piece" => {
+                return Err(error(
+                    line_number,
+                    first.column,
+                    "missing `end` before `piece`",
+                ));
+            }
+            _ => {
+                let message = format!("unknown keyword `{}`", first.text);
+                return Err(error(line_number, first.column, &message));
+            }
+        }
+    }
+    if let Some(current) = piece {
+        let message = format!("piece `{}` is missing `end`", current.name);
+        return Err(error(current.line, 1, &message));
+    }
+    return Ok(definitions);
+}
+
+impl PieceBuilder {
+    fn new(name: &str, line: usize) -> PieceBuilder {
+        return PieceBuilder {
+            name: name.to_string(),
+            line,
+            color: None,
+            spawn_offset: Point { x: 0, y: 0 },
+            wall_kicks: vec![],
+            fixed_rotation: false,
+            shapes: vec![],
+            shape_lines: vec![],
+            reading_shape: false,
+        };
+    }
+
+    fn push_grid_line(&mut self, text: &str) {
+        let row = text
+            .chars()
+            .map(|cell| if cell == '.' { 0 } else { 1 })
+            .collect();
+        if let Some(shape) = self.shapes.last_mut() {
+            shape.push(row);
+        }
+    }
+
+    fn build(&self, line: usize, column: usize) -> Result<PieceDefinition, PieceParseError> {
+        let color = match &self.color {
+            Some(color) => color.clone(),
+            None => return Err(error(line, column, "missing `color`")),
+        };
+        if self.shapes.is_empty() {
+            return Err(error(line, column, "missing `shape`"));
+        }
+        for (shape, shape_line) in self.shapes.iter().zip(self.shape_lines.iter()) {
+            validate_shape(shape, *shape_line, self.shapes.len() == 1)?;
+        }
+        let mut definition = PieceDefinition::new(&self.name, self.shapes[0].clone(), color);
+        definition.spawn_offset = self.spawn_offset;
+        if self.fixed_rotation {
+            definition.wall_kicks = vec![vec![]];
+        } else if !self.wall_kicks.is_empty() {
+            definition.wall_kicks = self.wall_kicks.clone();
+        }
+        if self.shapes.len() > 1 {
+            if self.fixed_rotation || self.shapes.len() != definition.wall_kicks.len() {
+                let message = format!(
+                    "{} shapes need {} `kicks` lines, found {}",
+                    self.shapes.len(),
+                    self.shapes.len(),
+                    definition.rotation_steps()
+                );
+                return Err(error(line, column, &message));
+            }
+            definition.rotation_shapes = self.shapes.clone();
+        } else if !self.fixed_rotation && definition.wall_kicks.len() != ROTATIONS {
+            let message = format!(
+                "a rotated shape needs {} `kicks` lines, found {}",
+                ROTATIONS,
+                definition.wall_kicks.len()
+            );
+            return Err(error(line, column, &message));
+        }
+        return Ok(definition);
+    }
+}
+
+fn tokenize(line: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut start: Option<usize> = None;
+    for (index, character) in line.char_indices() {
+        if character.is_whitespace() {
+            if let Some(token_start) = start.take() {
+                tokens.push(token(line, token_start, index));
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(token_start) = start {
+        tokens.push(token(line, token_start, line.len()));
+    }
+    return tokens;
+}
+
+fn token(line: &str, start: usize, end: usize) -> Token<'_> {
+    return Token {
+        text: &line[start..end],
+        column: line[..start].chars().count() + 1,
+    };
+}
+
+fn is_grid_line(text: &str) -> bool {
+    return text.chars().all(|cell| cell == '.' || cell == 'X');
+}
+
+fn validate_shape(shape: &[Vec<u8>], line: usize, square: bool) -> Result<(), PieceParseError> {
+    if shape.is_empty() {
+        return Err(error(line, 1, "empty shape"));
+    }
+    let width = shape[0].len();
+    for (index, row) in shape.iter().enumerate() {
+        if row.len() != width {
+            let message = format!("expected {} cells, found {}", width, row.len());
+            return Err(error(line + index + 1, 1, &message));
+        }
+    }
+    if square && width != shape.len() {
+        let message = format!(
+            "a rotated shape must be square, found {}x{}",
+            width,
+            shape.len()
+        );
+        return Err(error(line + 1, 1, &message));
+    }
+    if shape.iter().flatten().all(|cell| *cell == 0) {
+        return Err(error(line + 1, 1, "shape has no blocks"));
+    }
+    return Ok(());
+}
+
+fn expect_single<'a>(
+    tokens: &'a [Token<'a>],
+    line: usize,
+    expected: &str,
+) -> Result<&'a Token<'a>, PieceParseError> {
+    if tokens.len() < 2 {
+        let column = tokens[0].column + tokens[0].text.len();
+        return Err(error(line, column, &format!("expected {}", expected)));
+    }
+    if tokens.len() > 2 {
+        return Err(error(line, tokens[2].column, "unexpected text"));
+    }
+    return Ok(&tokens[1]);
+}
+
+fn parse_color(tokens: &[Token], line: usize) -> Result<Color, PieceParseError> {
+    let values = parse_numbers::<u8>(tokens, line, 3, "a color component (0-255)")?;
+    return Ok(Color {
+        red: values[0] as f32 / 255.0,
+        green: values[1] as f32 / 255.0,
+        blue: values[2] as f32 / 255.0,
+        alpha: 1.0,
+    });
+}
+
+fn parse_spawn(tokens: &[Token], line: usize) -> Result<Point, PieceParseError> {
+    let values = parse_numbers::<i32>(tokens, line, 2, "a number")?;
+    return Ok(Point {
+        x: values[0],
+        y: values[1],
+    });
+}
+
+fn parse_numbers<T: std::str::FromStr>(
+    tokens: &[Token],
+    line: usize,
+    count: usize,
+    expected: &str,
+) -> Result<Vec<T>, PieceParseError> {
+    let arguments = &tokens[1..];
+    if arguments.len() > count {
+        return Err(error(line, arguments[count].column, "unexpected text"));
+    }
+    if arguments.len() < count {
+        let last = tokens.last().map_or(&tokens[0], |token| token);
+        let column = last.column + last.text.len();
+        let message = format!("expected {} values, found {}", count, arguments.len());
+        return Err(error(line, column, &message));
+    }
+    return arguments
+        .iter()
+        .map(|token| {
+            return token.text.parse::<T>().map_err(|_| {
+                let message = format!("expected {}, found `{}`", expected, token.text);
+                return error(line, token.column, &message);
+            });
+        })
+        .collect();
+}
+
+fn parse_kicks(
+    piece: &mut PieceBuilder,
+    tokens: &[Token],
+    line: usize,
+) -> Result<(), PieceParseError> {
+    if tokens.len() == 2 && tokens[1].text == "none" {
+        piece.fixed_rotation = true;
+        return Ok(());
+    }
+    if tokens.len() < 2 {
+        let column = tokens[0].column + tokens[0].text.len();
+        return Err(error(line, column, "expected kick offsets like `0,0`"));
+    }
+    let mut kicks = vec![];
+    for token in &tokens[1..] {
+        let mut parts = token.text.split(',');
+        let x = parts.next().and_then(|part| part.parse::<i32>().ok());
+        let y = parts.next().and_then(|part| part.parse::<i32>().ok());
+        match (x, y, parts.next()) {
+            (Some(x), Some(y), None) => kicks.push(Point { x, y }),
+            _ => {
+                let message = format!("expected a kick offset like `0,0`, found `{}`", token.text);
+                return Err(error(line, token.column, &message));
+            }
+        }
+    }
+    piece.wall_kicks.push(kicks);
+    return Ok(());
+}
+
+fn error(line: usize, column: usize, message: &str) -> PieceParseError {
+    return PieceParseError {
+        line,
+        column,
+        message: message.to_string(),
+    };
+}
+
+#[cfg(test)]
+mod piece_parser_tests {
+    use super::super::figure_type::FigureType;
+    use super::*;
+
+    const T_PIECE: &str = "
+# The T figure
+piece T
+color 146 45 231
+shape
+.X.
+XXX
+...
+end
+";
+
+    #[test]
+    fn test_parse_rotated_shape() {
+        let definitions = parse_pieces(T_PIECE).unwrap();
+        assert_eq!(definitions.len(), 1);
+        let expectation = FigureType::T.definition();
+        assert_eq!(definitions[0].name, "T");
+        assert_eq!(definitions[0].shape, expectation.shape);
+        assert_eq!(definitions[0].wall_kicks, expectation.wall_kicks);
+        assert!(definitions[0].rotation_shapes.is_empty());
+    }
+    #[test]
+    fn test_parse_rotation_states() {
+        let text = "
+piece bar
+color 0 0 0
+spawn 1 -1
+kicks 0,0 -1,0
+kicks 0,0 1,0
+shape
+XX
+shape
+X.
+X.
+end
+piece dot
+color 255 255 255
+kicks none
+shape
+X
+end
+";
+        let definitions = parse_pieces(text).unwrap();
+        assert_eq!(definitions.len(), 2);
+        assert_eq!(definitions[0].spawn_offset, Point { x: 1, y: -1 });
+        assert_eq!(
+            definitions[0].wall_kicks,
+            vec![
+                vec![Point { x: 0, y: 0 }, Point { x: -1, y: 0 }],
+                vec![Point { x: 0, y: 0 }, Point { x: 1, y: 0 }],
+            ]
+        );
+        assert_eq!(
+            definitions[0].rotation_shapes,
+            vec![vec![vec![1, 1]], vec![vec![1, 0], vec![1, 0]]]
+        );
+        assert_eq!(definitions[1].rotation_steps(), 1);
+    }
+    #[test]
+    fn test_rotated_shape_kicks_error() {
+        let text = "piece bar\ncolor 1 2 3\nkicks 0,0\nkicks 0,0\nshape\nXX\nXX\nend";
+        let error = parse_pieces(text).unwrap_err();
+        assert_eq!(error.line, 8);
+        assert_eq!(
+            error.message,
+            "a rotated shape needs 4 `kicks` lines, found 2"
+        );
+    }
+    #[test]
+    fn test_unknown_keyword_error() {
+        let text = "piece T\n  colour 1 2 3\nend";
+        let error = parse_pieces(text).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(
+            error.to_string(),
+            "line 2, column 3: unknown keyword `colour`"
+        );
+    }
+    #[test]
+    fn test_bad_number_error() {
+        let error = parse_pieces("piece T\ncolor 1 300 3\nend").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 9));
+    }
+    #[test]
+    fn test_bad_kick_error() {
+        let error = parse_pieces("piece T\nkicks 0,0 1;0\nend").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 11));
+    }
+    #[test]
+    fn test_uneven_shape_error() {
+        let error = parse_pieces("piece T\ncolor 1 2 3\nshape\nXXX\nX.\nXXX\nend").unwrap_err();
+        assert_eq!(error.line, 5);
+    }
+    #[test]
+    fn test_big_shape() {
+        let pieces =
+            parse_pieces("piece big\ncolor 1 2 3\nshape\nXXXXX\nXXXXX\nXXXXX\nXXXXX\nXXXXX\nend");
+        assert_eq!(pieces.unwrap()[0].blocks_count(), 25);
+    }
+    #[test]
+    fn test_missing_parts_errors() {
+        let missing_end = parse_pieces("piece T\ncolor 1 2 3\nshape\nX").unwrap_err();
+        assert_eq!(missing_end.line, 1);
+        let missing_color = parse_pieces("piece T\nshape\nX\nend").unwrap_err();
+        assert_eq!(missing_color.message, "missing `color`");
+        let missing_kicks = parse_pieces("piece T\ncolor 1 2 3\nshape\nX\nshape\nX\nend");
+        assert_eq!(missing_kicks.unwrap_err().line, 7);
+    }
+}
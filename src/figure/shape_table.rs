@@ -0,0 +1,246 @@
+use super::geometry::Point;
+use super::matrix::Matrix;
+
+// Blocks of the biggest built-in figure.
+const MAX_BLOCKS: usize = 4;
+
+const ORIGIN: Point = Point { x: 0, y: 0 };
+
+pub const I_SHAPE: [[u8; 4]; 4] = [[0, 0, 0, 0], [1, 1, 1, 1], [0, 0, 0, 0], [0, 0, 0, 0]];
+pub const J_SHAPE: [[u8; 3]; 3] = [[1, 0, 0], [1, 1, 1], [0, 0, 0]];
+pub const L_SHAPE: [[u8; 3]; 3] = [[0, 0, 1], [1, 1, 1], [0, 0, 0]];
+pub const O_SHAPE: [[u8; 2]; 2] = [[1, 1], [1, 1]];
+pub const S_SHAPE: [[u8; 3]; 3] = [[0, 1, 1], [1, 1, 0], [0, 0, 0]];
+pub const T_SHAPE: [[u8; 3]; 3] = [[0, 1, 0], [1, 1, 1], [0, 0, 0]];
+pub const Z_SHAPE: [[u8; 3]; 3] = [[1, 1, 0], [0, 1, 1], [0, 0, 0]];
+pub const GARBAGE_SHAPE: [[u8; 1]; 1] = [[1]];
+
+// Every rotation state of the built-in figures, computed at compile time.
+pub static I_BLOCKS: [Blocks; 4] = Blocks::rotations(I_SHAPE);
+pub static J_BLOCKS: [Blocks; 4] = Blocks::rotations(J_SHAPE);
+pub static L_BLOCKS: [Blocks; 4] = Blocks::rotations(L_SHAPE);
+pub static O_BLOCKS: [Blocks; 4] = Blocks::rotations(O_SHAPE);
+pub static S_BLOCKS: [Blocks; 4] = Blocks::rotations(S_SHAPE);
+pub static T_BLOCKS: [Blocks; 4] = Blocks::rotations(T_SHAPE);
+pub static Z_BLOCKS: [Blocks; 4] = Blocks::rotations(Z_SHAPE);
+pub static GARBAGE_BLOCKS: [Blocks; 4] = Blocks::rotations(GARBAGE_SHAPE);
+
+pub fn shape_to_vec<const N: usize>(shape: [[u8; N]; N]) -> Vec<Vec<u8>> {
+    return shape.iter().map(|row| row.to_vec()).collect();
+}
+
+// The blocks of a built-in figure in one rotation state, sorted by row and
+// column, with their bounds. Kept in a fixed-size array so they can be static.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blocks {
+    points: [Point; MAX_BLOCKS],
+    len: usize,
+    min: Point,
+    max: Point,
+}
+
+impl Blocks {
+    const EMPTY: Blocks = Blocks {
+        points: [ORIGIN; MAX_BLOCKS],
+        len: 0,
+        min: ORIGIN,
+        max: ORIGIN,
+    };
+
+    pub fn view(&self) -> BlockView<'_> {
+        return BlockView {
+            points: &self.points[..self.len],
+            min: self.min,
+            max: self.max,
+        };
+    }
+
+    // Clockwise rotations of a square shape, starting with the shape itself.
+    const fn rotations<const N: usize>(shape: [[u8; N]; N]) -> [Blocks; 4] {
+        let mut rotations = [Blocks::EMPTY; 4];
+        let mut shape = shape;
+        let mut rotation = 0;
+        while rotation < 4 {
+            rotations[rotation] = Blocks::from_grid(shape);
+            shape = rotated_grid(shape);
+            rotation += 1;
+        }
+        return rotations;
+    }
+
+    const fn from_grid<const N: usize>(shape: [[u8; N]; N]) -> Blocks {
+        let mut blocks = Blocks::EMPTY;
+        let mut y = 0;
+        while y < N {
+            let mut x = 0;
+            while x < N {
+                if shape[y][x] == 1 {
+                    blocks = blocks.adding(x as i32, y as i32);
+                }
+                x += 1;
+            }
+            y += 1;
+        }
+        return blocks;
+    }
+
+    const fn adding(self, x: i32, y: i32) -> Blocks {
+        assert!(
+            self.len < MAX_BLOCKS,
+            "built-in figures can't have more than 4 blocks"
+        );
+        let mut blocks = self;
+        blocks.points[blocks.len] = Point { x, y };
+        if blocks.len == 0 {
+            blocks.min = Point { x, y };
+            blocks.max = Point { x, y };
+        } else {
+            blocks.min = Point {
+                x: min(blocks.min.x, x),
+                y: min(blocks.min.y, y),
+            };
+            blocks.max = Point {
+                x: max(blocks.max.x, x),
+                y: max(blocks.max.y, y),
+            };
+        }
+        blocks.len += 1;
+        return blocks;
+    }
+}
+
+// The blocks of a custom figure in one rotation state, with no limit on their
+// count. Built once when the figure is created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockList {
+    points: Vec<Point>,
+    min: Point,
+    max: Point,
+}
+
+impl BlockList {
+    pub fn from_matrix(matrix: &Matrix<u8>) -> BlockList {
+        let mut points = vec![];
+        for y in 0..matrix.height() {
+            for x in 0..matrix.width() {
+                if matrix.at_xy(x, y) == Some(&1) {
+                    points.push(Point {
+                        x: x as i32,
+                        y: y as i32,
+                    });
+                }
+            }
+        }
+        let first = points.first().cloned().unwrap_or(ORIGIN);
+        let (min, max) = points.iter().fold((first, first), |(min, max), point| {
+            return (
+                Point {
+                    x: min.x.min(point.x),
+                    y: min.y.min(point.y),
+                },
+                Point {
+                    x: max.x.max(point.x),
+                    y: max.y.max(point.y),
+                },
+            );
+        });
+        return BlockList { points, min, max };
+    }
+
+    pub fn view(&self) -> BlockView<'_> {
+        return BlockView {
+            points: &self.points,
+            min: self.min,
+            max: self.max,
+        };
+    }
+}
+
+// Borrowed blocks of either kind, read without allocating.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockView<'a> {
+    points: &'a [Point],
+    min: Point,
+    max: Point,
+}
+
+impl<'a> BlockView<'a> {
+    pub fn as_slice(&self) -> &'a [Point] {
+        return self.points;
+    }
+
+    // Top left corner of the bounding box.
+    pub fn min(&self) -> Point {
+        return self.min;
+    }
+
+    // Bottom right corner of the bounding box.
+    pub fn max(&self) -> Point {
+        return self.max;
+    }
+}
+
+const fn rotated_grid<const N: usize>(shape: [[u8; N]; N]) -> [[u8; N]; N] {
+    let mut rotated = [[0; N]; N];
+    let mut y = 0;
+    while y < N {
+        let mut x = 0;
+        while x < N {
+            rotated[y][x] = shape[N - 1 - x][y];
+            x += 1;
+        }
+        y += 1;
+    }
+    return rotated;
+}
+
+const fn min(first: i32, second: i32) -> i32 {
+    if first < second {
+        return first;
+    }
+    return second;
+}
+
+const fn max(first: i32, second: i32) -> i32 {
+    if first > second {
+        return first;
+    }
+    return second;
+}
+
+#[cfg(test)]
+mod shape_table_tests {
+    use super::*;
+
+    #[test]
+    fn test_static_rotations() {
+        let expectation = vec![
+            Point { x: 1, y: 0 },
+            Point { x: 1, y: 1 },
+            Point { x: 2, y: 1 },
+            Point { x: 1, y: 2 },
+        ];
+        assert_eq!(T_BLOCKS[1].view().as_slice(), expectation.as_slice());
+        assert_eq!(O_BLOCKS[3], O_BLOCKS[0]);
+    }
+    #[test]
+    fn test_bounds() {
+        assert_eq!(I_BLOCKS[0].view().min(), Point { x: 0, y: 1 });
+        assert_eq!(I_BLOCKS[0].view().max(), Point { x: 3, y: 1 });
+        assert_eq!(I_BLOCKS[1].view().min(), Point { x: 2, y: 0 });
+        assert_eq!(I_BLOCKS[1].view().max(), Point { x: 2, y: 3 });
+    }
+    #[test]
+    fn test_from_matrix() {
+        let matrix = Matrix::new(shape_to_vec(S_SHAPE));
+        assert_eq!(BlockList::from_matrix(&matrix).view(), S_BLOCKS[0].view());
+        let rotated = BlockList::from_matrix(&matrix.rotated());
+        assert_eq!(rotated.view(), S_BLOCKS[1].view());
+    }
+    #[test]
+    fn test_block_list_has_no_limit() {
+        let blocks = BlockList::from_matrix(&Matrix::new(vec![vec![1; 20], vec![0; 20]]));
+        assert_eq!(blocks.view().as_slice().len(), 20);
+        assert_eq!(blocks.view().max(), Point { x: 19, y: 0 });
+    }
+}
@@ -4,6 +4,7 @@ pub struct Point {
     pub y: i32,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UPoint {
     pub x: u32,
@@ -1,3 +1,3 @@
+pub mod block;
 pub mod geometry;
 pub mod graphics;
-pub mod block;
@@ -0,0 +1,96 @@
+use super::move_validator::has_valid_position;
+use super::placement::{dropped, successors};
+use super::{Action, ActiveFigure, Board, Placement};
+use std::collections::{HashSet, VecDeque};
+
+// Fewest inputs (moves and rotations, soft drops aside) taking a figure from
+// its spawn position to where `locked` lands with a hard drop. Placements
+// taking the same cells are equivalent. None when the lock position can't be
+// reached with a hard drop from above, like tucks and spins.
+pub fn minimal_inputs(board: &Board, spawned: &ActiveFigure, locked: &ActiveFigure) -> Option<u32> {
+    if !has_valid_position(spawned, board) {
+        return None;
+    }
+    let target = Placement::new(locked.clone(), board);
+    let mut visited = HashSet::new();
+    let mut pending = VecDeque::new();
+    visited.insert(state_key(spawned));
+    pending.push_back((spawned.clone(), 0));
+    while let Some((state, inputs)) = pending.pop_front() {
+        if Placement::new(dropped(board, &state), board).cells() == target.cells() {
+            return Some(inputs);
+        }
+        for (action, next) in successors(board, &state) {
+            if action != Action::MoveDown && visited.insert(state_key(&next)) {
+                pending.push_back((next, inputs + 1));
+            }
+        }
+    }
+    return None;
+}
+
+// Inputs the player can make for every figure. Soft and hard drops aren't
+// finesse inputs.
+pub(crate) fn is_finesse_input(action: Action) -> bool {
+    return match action {
+        Action::MoveLeft | Action::MoveRight | Action::Rotate => true,
+        Action::MoveDown | Action::HardDrop => false,
+    };
+}
+
+fn state_key(figure: &ActiveFigure) -> (i32, i32, usize) {
+    let position = figure.position();
+    return (position.x, position.y, figure.rotation_step());
+}
+
+#[cfg(test)]
+mod finesse_tests {
+    use super::super::{FigureType, Point, Size};
+    use super::*;
+
+    fn get_board() -> Board {
+        return Board::new(&Size {
+            height: 20,
+            width: 10,
+        });
+    }
+
+    fn locked(board: &Board, figure: ActiveFigure) -> ActiveFigure {
+        return dropped(board, &figure);
+    }
+
+    #[test]
+    fn test_straight_drop_needs_no_inputs() {
+        let board = get_board();
+        let spawned = ActiveFigure::new(FigureType::O, Point { x: 3, y: 0 });
+        let target = locked(&board, spawned.clone());
+        assert_eq!(minimal_inputs(&board, &spawned, &target), Some(0));
+    }
+    #[test]
+    fn test_moves_to_the_wall() {
+        let board = get_board();
+        let spawned = ActiveFigure::new(FigureType::T, Point { x: 3, y: 0 });
+        let target = locked(&board, spawned.moved_left().moved_left().moved_left());
+        assert_eq!(minimal_inputs(&board, &spawned, &target), Some(3));
+    }
+    #[test]
+    fn test_equivalent_rotations() {
+        let board = get_board();
+        let spawned = ActiveFigure::new(FigureType::S, Point { x: 3, y: 0 });
+        // Three rotations take the same cells as one rotation and a move.
+        let rotated = spawned.rotated().rotated().rotated();
+        let target = locked(&board, rotated);
+        assert_eq!(minimal_inputs(&board, &spawned, &target), Some(2));
+    }
+    #[test]
+    fn test_tuck_is_not_judged() {
+        let mut board = get_board();
+        for x in 0..8 {
+            board = board.replacing_figure_at_xy(x, 17, Some(FigureType::I));
+        }
+        let spawned = ActiveFigure::new(FigureType::O, Point { x: 3, y: 0 });
+        let tucked = ActiveFigure::new(FigureType::O, Point { x: 3, y: 18 });
+        assert!(!has_valid_position(&tucked.moved_down(), &board));
+        assert_eq!(minimal_inputs(&board, &spawned, &tucked), None);
+    }
+}
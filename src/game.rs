@@ -1,13 +1,33 @@
+use super::finesse::{is_finesse_input, minimal_inputs};
+use super::game_mode::{GameMode, GameResult, PuzzleGoal};
+use super::garbage::GarbageGenerator;
+use super::master::{level_after_lock, MasterTiming};
 use super::move_validator::{can_move_down, has_valid_position};
-use super::{ActiveFigure, Block, Board, FigureType, Point, Size};
+use super::piece_generator::{PieceGenerator, QueueGenerator, RandomGenerator};
+use super::placement::{path_to, reachable_placements, Placement};
+use super::{
+    ActiveFigure, Block, Board, CellKind, Color, FigureType, LineClear, LineClearGravity, Point,
+    SeededRandomizer, Size, Visibility,
+};
+use std::collections::VecDeque;
+use std::rc::Rc;
 
 const MOVING_PERIOD: f64 = 0.2; //secs
 
+const OBSTACLE_COLOR: Color = Color {
+    red: 64.0 / 255.0,
+    green: 64.0 / 255.0,
+    blue: 64.0 / 255.0,
+    alpha: 1.0,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Action {
     MoveDown,
     MoveLeft,
     MoveRight,
     Rotate,
+    HardDrop,
 }
 
 pub trait Randomizer {
@@ -18,25 +38,116 @@ pub trait Randomizer {
 pub enum GameState {
     Playing,
     GameOver,
+    Finished,
+}
+
+struct IncomingGarbage {
+    lines: u32,
+    delay: f64,
 }
 
 pub struct Game {
     board: Board,
     score: u64,
     active: ActiveFigure,
-    next: ActiveFigure,
+    next: Option<ActiveFigure>,
     waiting_time: f64,
-    randomizer: Box<dyn Randomizer + 'static>,
+    randomizer: Rc<dyn Randomizer + 'static>,
+    generator: Box<dyn PieceGenerator + 'static>,
     state: GameState,
+    mode: GameMode,
+    elapsed_time: f64,
+    lines: u32,
+    pieces: u32,
+    garbage: GarbageGenerator,
+    incoming_garbage: VecDeque<IncomingGarbage>,
+    last_line_clear: Option<LineClear>,
+    combo: Option<u32>,
+    back_to_back: bool,
+    rotated_last: bool,
+    master_level: u32,
+    lock_time: f64,
+    gravity_progress: f64,
+    spawn_timer: Option<f64>,
+    visibility: Visibility,
+    figure_scale: i32,
+    line_clear_gravity: LineClearGravity,
+    color_match_size: Option<usize>,
+    rise_timer: Option<f64>,
+    piece_inputs: u32,
+    finesse_tracking: bool,
+    finesse_faults: u32,
 }
 
 impl Game {
     pub fn new(size: &Size, randomizer: Box<dyn Randomizer + 'static>) -> Game {
-        let start_point = Game::figure_start_point(size.width);
-        let active = Game::random_figure(start_point, &randomizer);
-        let next = Game::random_figure(start_point, &randomizer);
+        return Game::with_mode(size, randomizer, GameMode::Endless);
+    }
 
+    pub fn with_mode(
+        size: &Size,
+        randomizer: Box<dyn Randomizer + 'static>,
+        mode: GameMode,
+    ) -> Game {
+        let randomizer: Rc<dyn Randomizer + 'static> = Rc::from(randomizer);
+        let generator = Box::new(RandomGenerator::new(randomizer.clone()));
+        return Game::with_shared_randomizer(Board::new(size), randomizer, mode, generator);
+    }
+
+    pub fn with_generator(
+        size: &Size,
+        randomizer: Box<dyn Randomizer + 'static>,
+        mode: GameMode,
+        generator: Box<dyn PieceGenerator + 'static>,
+    ) -> Game {
         let board = Board::new(size);
+        return Game::with_shared_randomizer(board, Rc::from(randomizer), mode, generator);
+    }
+
+    // Plays on a custom board, e.g. one with obstacles or masked cells.
+    pub fn with_board(
+        board: Board,
+        randomizer: Box<dyn Randomizer + 'static>,
+        mode: GameMode,
+        generator: Box<dyn PieceGenerator + 'static>,
+    ) -> Game {
+        return Game::with_shared_randomizer(board, Rc::from(randomizer), mode, generator);
+    }
+
+    // Plays the given figures in order on a prefilled board. The puzzle fails
+    // when the figures run out before reaching the goal.
+    pub fn puzzle(board: Board, figures: Vec<FigureType>, goal: PuzzleGoal) -> Game {
+        let randomizer = Rc::new(SeededRandomizer::new(0));
+        let generator = Box::new(QueueGenerator::new(figures));
+        let mode = GameMode::Puzzle { goal };
+        return Game::with_shared_randomizer(board, randomizer, mode, generator);
+    }
+
+    fn with_shared_randomizer(
+        board: Board,
+        randomizer: Rc<dyn Randomizer + 'static>,
+        mode: GameMode,
+        mut generator: Box<dyn PieceGenerator + 'static>,
+    ) -> Game {
+        let width = board.width();
+        let first_figure = generator.next_figure();
+        let state = match first_figure {
+            Some(_) => GameState::Playing,
+            None => GameState::GameOver,
+        };
+        let active = Game::spawned_figure(first_figure.unwrap_or(FigureType::O), width, 1);
+        let next = generator
+            .next_figure()
+            .map(|figure| Game::spawned_figure(figure, width, 1));
+
+        let mut garbage = GarbageGenerator::new(mode.same_column_probability());
+        let garbage_lines = mode
+            .initial_garbage_lines()
+            .min(board.height().saturating_sub(1));
+        let holes = garbage.next_holes(garbage_lines, board.width(), randomizer.as_ref());
+        let mut board = board;
+        board.insert_garbage_lines(&holes, 0.0);
+        let rise_timer = mode.rise_interval(0.0);
         return Game {
             board,
             score: 0,
@@ -44,32 +155,138 @@ impl Game {
             next,
             waiting_time: 0.0,
             randomizer,
-            state: GameState::Playing,
+            generator,
+            state,
+            mode,
+            elapsed_time: 0.0,
+            lines: 0,
+            pieces: 0,
+            garbage,
+            incoming_garbage: VecDeque::default(),
+            last_line_clear: None,
+            combo: None,
+            back_to_back: false,
+            rotated_last: false,
+            master_level: 0,
+            lock_time: 0.0,
+            gravity_progress: 0.0,
+            spawn_timer: None,
+            visibility: Visibility::default(),
+            figure_scale: 1,
+            line_clear_gravity: LineClearGravity::default(),
+            color_match_size: None,
+            rise_timer,
+            piece_inputs: 0,
+            finesse_tracking: false,
+            finesse_faults: 0,
         };
     }
 
-    fn figure_start_point(width: usize) -> Point {
+    // In Big mode the start point is kept on the scaled grid, so figures can
+    // reach both walls moving two columns at a time.
+    fn figure_start_point(width: usize, scale: i32) -> Point {
+        if scale > 1 {
+            let grid_width = width as i32 / scale;
+            let mid_point = ((grid_width + 1) / 2 - 2).max(0);
+            return Point {
+                x: mid_point * scale,
+                y: 0,
+            };
+        }
         let mid_point = (width as i32).wrapping_div(2) - 2;
         return Point { x: mid_point, y: 0 };
     }
 
-    fn random_figure(position: Point, randomizer: &Box<dyn Randomizer + 'static>) -> ActiveFigure {
-        let figure = match randomizer.random_between(0, 6) {
-            0 => FigureType::I,
-            1 => FigureType::J,
-            2 => FigureType::L,
-            3 => FigureType::O,
-            4 => FigureType::S,
-            5 => FigureType::T,
-            _ => FigureType::Z,
+    pub fn set_big_mode(&mut self, big_mode: bool) {
+        self.figure_scale = if big_mode { 2 } else { 1 };
+        self.active = self.new_active_figure(self.active.get_type());
+        self.next = self
+            .next
+            .as_ref()
+            .map(|next| self.new_active_figure(next.get_type()));
+    }
+
+    // Cylindrical board: figures moving past one side re-enter from the other.
+    pub fn set_wraparound(&mut self, wraps: bool) {
+        self.board = self.board.wrapping_around(wraps);
+    }
+
+    pub fn is_wraparound(&self) -> bool {
+        return self.board.is_wrapping_around();
+    }
+
+    pub fn set_line_clear_gravity(&mut self, gravity: LineClearGravity) {
+        self.line_clear_gravity = gravity;
+    }
+
+    pub fn get_line_clear_gravity(&self) -> LineClearGravity {
+        return self.line_clear_gravity;
+    }
+
+    // Experimental: groups of at least `min_size` connected blocks of the same
+    // figure type are cleared too, like full lines. The blocks left floating
+    // then fall as chunks even with naive gravity, so clears can chain.
+    pub fn set_color_match(&mut self, min_size: Option<usize>) {
+        self.color_match_size = min_size;
+    }
+
+    pub fn get_color_match(&self) -> Option<usize> {
+        return self.color_match_size;
+    }
+
+    // Off by default: judging every lock searches the fewest inputs.
+    pub fn set_finesse_tracking(&mut self, tracking: bool) {
+        self.finesse_tracking = tracking;
+    }
+
+    pub fn is_finesse_tracking(&self) -> bool {
+        return self.finesse_tracking;
+    }
+
+    pub fn is_big_mode(&self) -> bool {
+        return self.figure_scale > 1;
+    }
+
+    fn new_active_figure(&self, figure_type: FigureType) -> ActiveFigure {
+        return Game::spawned_figure(figure_type, self.board.width(), self.figure_scale);
+    }
+
+    pub(crate) fn spawned_figure(
+        figure_type: FigureType,
+        width: usize,
+        scale: i32,
+    ) -> ActiveFigure {
+        let start_point = Game::figure_start_point(width, scale);
+        let offset = figure_type.definition().spawn_offset;
+        let position = Point {
+            x: start_point.x + offset.x * scale,
+            y: start_point.y + offset.y * scale,
         };
-        return ActiveFigure::new(figure, position);
+        return ActiveFigure::new(figure_type, position).scaled(scale);
     }
 
     pub fn is_game_over(&self) -> bool {
         return self.state == GameState::GameOver;
     }
 
+    pub fn is_finished(&self) -> bool {
+        return self.state != GameState::Playing;
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        if !self.is_finished() {
+            return None;
+        }
+        return Some(GameResult {
+            mode: self.mode.clone(),
+            completed: self.state == GameState::Finished,
+            time: self.elapsed_time,
+            lines: self.lines,
+            score: self.score,
+            pieces: self.pieces,
+        });
+    }
+
     // DRAWING FUNCTIONS
 
     pub fn draw(&self) -> Vec<Block> {
@@ -79,9 +296,13 @@ impl Game {
     }
 
     fn draw_active_figure(&self) -> Vec<Block> {
+        if self.spawn_timer.is_some() {
+            return vec![];
+        }
         let figure = self.active.to_cartesian();
         return figure
             .iter()
+            .map(|point| self.board.wrapped_point(*point))
             .map(|point| Block::new(point.x, point.y, 1, 1, self.active.color()))
             .collect();
     }
@@ -90,8 +311,17 @@ impl Game {
         let mut blocks = vec![];
         for y in 0..self.board.height() {
             for x in 0..self.board.width() {
-                if let Some(square) = self.board.figure_at_xy(x, y) {
-                    let block = Block::new(x as i32, y as i32, 1, 1, square.color());
+                if self.board.cell_kind_at_xy(x, y) == CellKind::Obstacle {
+                    let block = Block::new(x as i32, y as i32, 1, 1, OBSTACLE_COLOR);
+                    blocks.push(block);
+                } else if let Some(square) = self.board.figure_at_xy(x, y) {
+                    let alpha = self.block_alpha(x, y);
+                    if alpha <= 0.0 {
+                        continue;
+                    }
+                    let mut color = square.color();
+                    color.alpha *= alpha;
+                    let block = Block::new(x as i32, y as i32, 1, 1, color);
                     blocks.push(block);
                 }
             }
@@ -99,18 +329,113 @@ impl Game {
         return blocks;
     }
 
+    // The whole stack is shown again once the game is finished.
+    fn block_alpha(&self, x: usize, y: usize) -> f32 {
+        if self.is_finished() {
+            return 1.0;
+        }
+        let lock_time = self.board.lock_time_at_xy(x, y);
+        return self.visibility.alpha_for(lock_time, self.elapsed_time);
+    }
+
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    pub fn get_visibility(&self) -> &Visibility {
+        return &self.visibility;
+    }
+
     // GAME UPDATE
 
     pub fn update(&mut self, delta_time: f64) {
-        self.waiting_time += delta_time;
-        if self.waiting_time > MOVING_PERIOD {
-            self.update_game();
-            self.waiting_time = 0.0;
+        if self.state != GameState::Playing {
+            return;
+        }
+        self.update_elapsed_time(delta_time);
+        self.update_incoming_garbage(delta_time);
+        self.update_rising_floor(delta_time);
+        if self.state != GameState::Playing {
+            return;
+        }
+        if self.mode == GameMode::Master {
+            self.update_master(delta_time);
+        } else {
+            self.waiting_time += delta_time;
+            if self.waiting_time > self.moving_period() {
+                self.update_game();
+                self.waiting_time = 0.0;
+            }
+        }
+        self.update_state();
+    }
+
+    fn update_elapsed_time(&mut self, delta_time: f64) {
+        self.elapsed_time += delta_time;
+        if let Some(time_limit) = self.mode.time_limit() {
+            self.elapsed_time = self.elapsed_time.min(time_limit);
         }
     }
 
+    fn update_incoming_garbage(&mut self, delta_time: f64) {
+        for garbage in self.incoming_garbage.iter_mut() {
+            garbage.delay -= delta_time;
+        }
+    }
+
+    // SURVIVAL MODE
+    // The floor rises on a timer, independent of the player actions.
+
+    fn update_rising_floor(&mut self, delta_time: f64) {
+        let mut rise_timer = match self.rise_timer {
+            Some(rise_timer) => rise_timer - delta_time,
+            None => return,
+        };
+        // A long update can cover several rises.
+        while rise_timer <= 0.0 && !self.is_game_over() {
+            self.rise_floor();
+            match self.mode.rise_interval(self.elapsed_time) {
+                Some(interval) => rise_timer += interval,
+                None => break,
+            }
+        }
+        self.rise_timer = Some(rise_timer);
+    }
+
+    // The active figure is pushed up when the new row reaches it, topping out
+    // if there is no room left.
+    fn rise_floor(&mut self) {
+        if !self.board.is_line_empty(0) {
+            self.state = GameState::GameOver;
+            return;
+        }
+        let width = self.board.width();
+        let holes = self.garbage.next_holes(1, width, self.randomizer.as_ref());
+        self.board.insert_garbage_lines(&holes, self.elapsed_time);
+        if !has_valid_position(&self.active, &self.board) {
+            let pushed = self.active.moved_up();
+            if pushed.top_edge() >= 0 && has_valid_position(&pushed, &self.board) {
+                self.active = pushed;
+            } else {
+                self.state = GameState::GameOver;
+            }
+        }
+    }
+
+    // Seconds left before the floor rises, only during the warning period.
+    pub fn get_floor_rise_warning(&self) -> Option<f64> {
+        let warning_time = self.mode.rise_warning_time()?;
+        return self
+            .rise_timer
+            .filter(|rise_timer| *rise_timer <= warning_time);
+    }
+
+    fn moving_period(&self) -> f64 {
+        return self.mode.moving_period(self.get_level(), MOVING_PERIOD);
+    }
+
     fn update_game(&mut self) {
-        if self.state == GameState::GameOver {
+        if self.state != GameState::Playing {
             return;
         }
         if can_move_down(&self.active, &self.board) {
@@ -121,45 +446,191 @@ impl Game {
     }
 
     fn update_next_figure(&mut self) {
-        self.add_active_figure_to_board();
-        let completed_lines_count = self.remove_completed_lines();
-        self.add_score_for(completed_lines_count);
+        self.lock_active_figure();
+        if self.is_mode_completed() {
+            self.state = GameState::Finished;
+            return;
+        }
         self.add_new_active_figure();
         self.update_state();
     }
 
+    // Returns the number of completed lines.
+    fn lock_active_figure(&mut self) -> usize {
+        let t_spin = self.is_t_spin();
+        self.check_finesse();
+        self.add_active_figure_to_board();
+        self.pieces += 1;
+        let chain_lines = self.remove_completed_lines();
+        let completed_lines_count = chain_lines.iter().sum();
+        self.add_score_for_chain(&chain_lines);
+        self.lines += completed_lines_count as u32;
+        self.register_line_clear(
+            completed_lines_count as u32,
+            t_spin,
+            chain_lines.len() as u32,
+        );
+        if completed_lines_count == 0 {
+            self.insert_ready_garbage();
+        }
+        return completed_lines_count;
+    }
+
+    // MASTER MODE
+    // Gravity, lock delay, ARE and line clear delay follow the level timing table.
+
+    fn update_master(&mut self, delta_time: f64) {
+        let timing = MasterTiming::for_level(self.master_level);
+        if let Some(spawn_timer) = self.spawn_timer {
+            let spawn_timer = spawn_timer - delta_time;
+            if spawn_timer > 0.0 {
+                self.spawn_timer = Some(spawn_timer);
+            } else {
+                self.spawn_master_figure();
+            }
+            return;
+        }
+        self.apply_master_gravity(delta_time, &timing);
+        if can_move_down(&self.active, &self.board) {
+            self.lock_time = 0.0;
+            return;
+        }
+        self.lock_time += delta_time;
+        if self.lock_time >= timing.lock_delay {
+            self.lock_master_figure(&timing);
+        }
+    }
+
+    fn apply_master_gravity(&mut self, delta_time: f64, timing: &MasterTiming) {
+        if timing.is_instant_gravity() {
+            self.drop_active_figure();
+            return;
+        }
+        self.gravity_progress += timing.rows_for(delta_time);
+        while self.gravity_progress >= 1.0 {
+            self.gravity_progress -= 1.0;
+            if !can_move_down(&self.active, &self.board) {
+                self.gravity_progress = 0.0;
+                break;
+            }
+            self.move_down();
+            self.lock_time = 0.0;
+        }
+    }
+
+    fn lock_master_figure(&mut self, timing: &MasterTiming) {
+        let completed_lines_count = self.lock_active_figure();
+        self.master_level = level_after_lock(self.master_level, completed_lines_count as u32);
+        self.lock_time = 0.0;
+        self.gravity_progress = 0.0;
+        if self.is_mode_completed() {
+            self.state = GameState::Finished;
+            return;
+        }
+        let mut spawn_delay = timing.are;
+        if completed_lines_count > 0 {
+            spawn_delay += timing.line_clear_delay;
+        }
+        self.spawn_timer = Some(spawn_delay);
+    }
+
+    fn spawn_master_figure(&mut self) {
+        self.spawn_timer = None;
+        self.add_new_active_figure();
+        if MasterTiming::for_level(self.master_level).is_instant_gravity() {
+            self.drop_active_figure();
+        }
+    }
+
+    fn drop_active_figure(&mut self) {
+        while can_move_down(&self.active, &self.board) {
+            self.move_down();
+        }
+    }
+
     fn update_state(&mut self) {
+        if self.state != GameState::Playing || self.spawn_timer.is_some() {
+            return;
+        }
         if self.check_is_game_over() {
             self.state = GameState::GameOver;
+        } else if self.is_mode_completed() {
+            self.state = GameState::Finished;
         }
     }
 
+    fn is_mode_completed(&self) -> bool {
+        return self.mode.is_completed(
+            self.lines,
+            self.get_level(),
+            self.elapsed_time,
+            &self.board,
+        );
+    }
+
     // MOVEMENT FUNCTIONS
 
     pub fn perform(&mut self, action: Action) {
+        if self.state != GameState::Playing || self.spawn_timer.is_some() {
+            return;
+        }
+        if is_finesse_input(action) {
+            self.piece_inputs += 1;
+        }
         match action {
             Action::MoveLeft => self.move_left(),
             Action::MoveRight => self.move_right(),
             Action::MoveDown => self.move_down(),
             Action::Rotate => self.rotate_active_figure(),
+            Action::HardDrop => {
+                self.hard_drop();
+                return;
+            }
+        }
+        if self.has_instant_gravity() {
+            self.drop_active_figure();
         }
     }
 
+    // Drops the figure to the bottom and locks it right away.
+    fn hard_drop(&mut self) {
+        self.drop_active_figure();
+        if self.mode == GameMode::Master {
+            self.lock_master_figure(&MasterTiming::for_level(self.master_level));
+        } else {
+            self.update_next_figure();
+            self.waiting_time = 0.0;
+        }
+    }
+
+    fn has_instant_gravity(&self) -> bool {
+        return self.mode == GameMode::Master
+            && MasterTiming::for_level(self.master_level).is_instant_gravity();
+    }
+
     fn move_left(&mut self) {
-        self.update_active_with(self.active.moved_left());
+        if self.update_active_with(self.active.moved_left()) {
+            self.rotated_last = false;
+        }
     }
 
     fn move_right(&mut self) {
-        self.update_active_with(self.active.moved_right());
+        if self.update_active_with(self.active.moved_right()) {
+            self.rotated_last = false;
+        }
     }
 
     fn move_down(&mut self) {
-        self.update_active_with(self.active.moved_down());
+        if self.update_active_with(self.active.moved_down()) {
+            self.rotated_last = false;
+        }
     }
 
     fn rotate_active_figure(&mut self) {
         if let Some(rotated) = self.wall_kicked_rotated_active_figure() {
-            self.update_active_with(rotated);
+            if self.update_active_with(rotated) {
+                self.rotated_last = true;
+            }
         }
     }
 
@@ -169,38 +640,106 @@ impl Game {
         return self
             .active
             .wall_kicked_rotation_tests()
-            .into_iter()
             .find(|figure| has_valid_position(figure, &self.board));
     }
 
     // Game state mutation
 
-    fn update_active_with(&mut self, new_active: ActiveFigure) {
+    fn update_active_with(&mut self, new_active: ActiveFigure) -> bool {
         if has_valid_position(&new_active, &self.board) {
-            self.active = new_active;
+            if self.board.is_wrapping_around() {
+                self.active = new_active.wrapped_around(self.board.width() as i32);
+            } else {
+                self.active = new_active;
+            }
+            return true;
+        }
+        return false;
+    }
+
+    // Counts a fault when the figure took more inputs than needed.
+    fn check_finesse(&mut self) {
+        if !self.finesse_tracking {
+            return;
+        }
+        let spawned = self.new_active_figure(self.active.get_type());
+        if let Some(minimal) = minimal_inputs(&self.board, &spawned, &self.active) {
+            if self.piece_inputs > minimal {
+                self.finesse_faults += 1;
+            }
         }
     }
 
     fn add_active_figure_to_board(&mut self) {
-        for point in self.active.to_cartesian() {
-            self.board = self.board.replacing_figure_at_xy(
+        let figure_type = self.active.get_type();
+        for point in self.active.cells() {
+            let point = self.board.wrapped_point(point);
+            let locked = self.board.set_locked(
                 point.x as usize,
                 point.y as usize,
-                Some(self.active.get_type()),
+                Some(figure_type.clone()),
+                self.elapsed_time,
             );
+            // The board can't hold one more figure type, so the game can't go on.
+            if locked.is_err() {
+                self.state = GameState::GameOver;
+            }
         }
     }
 
     fn add_new_active_figure(&mut self) {
-        let start_point = Game::figure_start_point(self.board.width());
-        self.update_active_with(self.next.clone());
-        self.next = Game::random_figure(start_point, &self.randomizer);
+        let next = match self.next.take() {
+            Some(next) => next,
+            None => {
+                self.state = GameState::GameOver;
+                return;
+            }
+        };
+        // A blocked spawn still replaces the locked figure, so it's detected as game over.
+        if !self.update_active_with(next.clone()) {
+            self.active = next;
+        }
+        self.rotated_last = false;
+        self.piece_inputs = 0;
+        self.next = self
+            .generator
+            .next_figure()
+            .map(|figure| self.new_active_figure(figure));
     }
 
-    fn remove_completed_lines(&mut self) -> usize {
-        let lines = self.lines_completed();
-        self.board = self.board.removing_lines(&lines);
-        return lines.len();
+    // Returns the lines cleared by every step of the chain.
+    fn remove_completed_lines(&mut self) -> Vec<usize> {
+        let mut chain_lines = vec![];
+        loop {
+            let lines = self.lines_completed();
+            let cells = self.matched_cells();
+            if lines.is_empty() && cells.is_empty() {
+                break;
+            }
+            if cells.is_empty() {
+                self.board
+                    .clear_rows_with_gravity(&lines, self.line_clear_gravity);
+            } else {
+                self.board
+                    .clear_cells_with_gravity(&lines, &cells, self.line_clear_gravity);
+            }
+            chain_lines.push(self.scaled_lines_count(lines.len()));
+            self.add_score_for_cells(cells.len(), chain_lines.len());
+        }
+        return chain_lines;
+    }
+
+    fn matched_cells(&self) -> Vec<(usize, usize)> {
+        if let Some(min_size) = self.color_match_size {
+            return self.board.matching_groups(min_size).concat();
+        }
+        return vec![];
+    }
+
+    // In Big mode every block is two rows high, so each two cleared rows count
+    // as one line (an odd row left by garbage still counts as one).
+    fn scaled_lines_count(&self, rows: usize) -> usize {
+        return rows.div_ceil(self.figure_scale as usize);
     }
 
     // Lines checks
@@ -216,10 +755,115 @@ impl Game {
     }
 
     fn is_line_completed(&self, line_number: usize) -> bool {
-        if let Some(line) = self.board.get_line(line_number) {
-            return !line.contains(&None);
+        return self.board.is_line_complete(line_number);
+    }
+
+    // T-Spin: the last movement was a rotation and 3 of the 4 corners
+    // around the T center are blocked.
+
+    fn is_t_spin(&self) -> bool {
+        if self.active.get_type() != FigureType::T || !self.rotated_last {
+            return false;
+        }
+        let position = self.active.position();
+        let scale = self.active.scale();
+        let corners = [(0, 0), (2, 0), (0, 2), (2, 2)];
+        let blocked_corners = corners
+            .iter()
+            .filter(|(x, y)| {
+                self.is_blocked(Point {
+                    x: position.x + x * scale,
+                    y: position.y + y * scale,
+                })
+            })
+            .count();
+        return blocked_corners >= 3;
+    }
+
+    fn is_blocked(&self, point: Point) -> bool {
+        let point = self.board.wrapped_point(point);
+        let outside_board = point.x < 0
+            || point.x >= self.board.width() as i32
+            || point.y >= self.board.height() as i32;
+        return outside_board || self.board.contains(point);
+    }
+
+    fn register_line_clear(&mut self, lines: u32, t_spin: bool, chain: u32) {
+        if lines == 0 {
+            self.combo = None;
+            return;
+        }
+        let combo = self.combo.map_or(0, |combo| combo + 1);
+        let mut line_clear = LineClear {
+            lines,
+            t_spin,
+            combo,
+            back_to_back: false,
+            perfect_clear: self.board.is_empty(),
+            chain,
+        };
+        line_clear.back_to_back = self.back_to_back && line_clear.is_difficult();
+        self.back_to_back = line_clear.is_difficult();
+        self.combo = Some(combo);
+        self.last_line_clear = Some(line_clear);
+    }
+
+    pub fn take_line_clear(&mut self) -> Option<LineClear> {
+        return self.last_line_clear.take();
+    }
+
+    // Garbage
+
+    pub fn receive_garbage(&mut self, lines: u32, delay: f64) {
+        if lines > 0 {
+            self.incoming_garbage
+                .push_back(IncomingGarbage { lines, delay });
+        }
+    }
+
+    pub fn get_incoming_garbage(&self) -> u32 {
+        return self
+            .incoming_garbage
+            .iter()
+            .map(|garbage| garbage.lines)
+            .sum();
+    }
+
+    // Cancels queued garbage with the given attack, returns the lines left to send.
+    pub fn counter_garbage(&mut self, attack: u32) -> u32 {
+        let mut attack = attack;
+        while attack > 0 {
+            if let Some(garbage) = self.incoming_garbage.front_mut() {
+                let countered = garbage.lines.min(attack);
+                garbage.lines -= countered;
+                attack -= countered;
+                if garbage.lines == 0 {
+                    self.incoming_garbage.pop_front();
+                }
+            } else {
+                break;
+            }
+        }
+        return attack;
+    }
+
+    fn insert_ready_garbage(&mut self) {
+        while let Some(garbage) = self.incoming_garbage.front() {
+            if garbage.delay > 0.0 {
+                break;
+            }
+            let lines = garbage.lines as usize;
+            self.incoming_garbage.pop_front();
+            if (0..lines).any(|line| !self.board.is_line_empty(line)) {
+                self.state = GameState::GameOver;
+            }
+            let width = self.board.width();
+            let holes = self.garbage.next_holes(1, width, self.randomizer.as_ref());
+            for hole in holes {
+                self.board
+                    .insert_garbage_lines(&vec![hole; lines], self.elapsed_time);
+            }
         }
-        return false;
     }
 
     // Score
@@ -228,17 +872,107 @@ impl Game {
         self.score += (completed_lines as u64) * 100;
     }
 
+    fn add_score_for_cells(&mut self, cells: usize, chain_step: usize) {
+        self.score += (cells * chain_step) as u64 * 10;
+    }
+
+    // Every step of a chain multiplies its score by its position in the chain.
+    fn add_score_for_chain(&mut self, chain_lines: &[usize]) {
+        for (step, completed_lines) in chain_lines.iter().enumerate() {
+            self.add_score_for(completed_lines * (step + 1));
+        }
+    }
+
     fn check_is_game_over(&self) -> bool {
-        return self.active.position().y == 0 && !has_valid_position(&self.active, &self.board);
+        let spawn_y = self.active.spawn_offset().y * self.active.scale();
+        return self.active.position().y == spawn_y
+            && !has_valid_position(&self.active, &self.board);
+    }
+
+    pub fn get_board(&self) -> &Board {
+        return &self.board;
+    }
+
+    pub fn get_active_figure(&self) -> &ActiveFigure {
+        return &self.active;
+    }
+
+    // Seconds left before the next figure spawns, while waiting for it.
+    pub fn get_spawn_delay(&self) -> Option<f64> {
+        return self.spawn_timer;
+    }
+
+    pub fn get_next_figure(&self) -> Option<&ActiveFigure> {
+        return self.next.as_ref();
+    }
+
+    pub fn reachable_placements(&self) -> Vec<Placement> {
+        return reachable_placements(&self.board, &self.active);
+    }
+
+    // Actions reaching the placement from the current active figure.
+    pub fn path_to(&self, placement: &Placement) -> Option<Vec<Action>> {
+        return path_to(&self.board, &self.active, placement);
+    }
+
+    // Moves and rotations performed on the active figure.
+    pub fn get_piece_inputs(&self) -> u32 {
+        return self.piece_inputs;
+    }
+
+    pub fn get_finesse_faults(&self) -> u32 {
+        return self.finesse_faults;
     }
 
     pub fn get_score(&self) -> u64 {
         return self.score;
     }
+
+    pub fn get_lines(&self) -> u32 {
+        return self.lines;
+    }
+
+    pub fn get_level(&self) -> u32 {
+        if self.mode == GameMode::Master {
+            return self.master_level;
+        }
+        return self.mode.level_for(self.lines);
+    }
+
+    pub fn get_pieces(&self) -> u32 {
+        return self.pieces;
+    }
+
+    pub fn get_elapsed_time(&self) -> f64 {
+        return self.elapsed_time;
+    }
+
+    pub fn get_pieces_per_second(&self) -> f64 {
+        if self.elapsed_time <= 0.0 {
+            return 0.0;
+        }
+        return self.pieces as f64 / self.elapsed_time;
+    }
+
+    pub fn get_lines_remaining(&self) -> Option<u32> {
+        return self
+            .mode
+            .line_goal()
+            .map(|line_goal| line_goal.saturating_sub(self.lines));
+    }
+
+    pub fn get_mode(&self) -> &GameMode {
+        return &self.mode;
+    }
+
+    pub fn get_garbage_lines(&self) -> usize {
+        return self.board.garbage_lines_count();
+    }
 }
 
 #[cfg(test)]
 mod game_tests {
+    use super::super::{PieceSet, SevenBagGenerator};
     use super::*;
 
     struct Random {
@@ -367,13 +1101,13 @@ mod game_tests {
     #[test]
     fn test_start_point_pair() {
         let width = 10;
-        let start_point = Game::figure_start_point(width);
+        let start_point = Game::figure_start_point(width, 1);
         assert_eq!(start_point.x, 3);
     }
     #[test]
     fn test_start_point_odd() {
         let width = 11;
-        let start_point = Game::figure_start_point(width);
+        let start_point = Game::figure_start_point(width, 1);
         assert_eq!(start_point.x, 3);
     }
     #[test]
@@ -417,11 +1151,720 @@ mod game_tests {
         let mut game = get_game_with_size(2, 2);
         assert_eq!(game.get_score(), 0);
 
-        game.active = ActiveFigure::new(FigureType::O, Point{ x: 0, y: 0});
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        game.update(10.0);
+
+        assert_eq!(game.get_score(), 200);
+    }
+
+    #[test]
+    fn test_lines_and_pieces_are_counted() {
+        let mut game = get_game_with_size(2, 2);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        game.update(10.0);
+
+        assert_eq!(game.get_lines(), 2);
+        assert_eq!(game.get_pieces(), 1);
+    }
+    #[test]
+    fn test_result_is_none_while_playing() {
+        let mut game = get_game();
+        update_loops(&mut game, 1);
+        assert!(!game.is_finished());
+        assert_eq!(game.result(), None);
+    }
+    #[test]
+    fn test_sprint_finishes_on_line_goal() {
+        let mut game = get_game_with_size_and_mode(2, 2, GameMode::Sprint { line_goal: 2 });
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        game.update(0.5);
+
+        let result = game.result().unwrap();
+        assert!(game.is_finished());
+        assert!(!game.is_game_over());
+        assert!(result.completed);
+        assert_eq!(result.lines, 2);
+        assert_eq!(result.pieces, 1);
+        assert_eq!(result.score, 200);
+        assert_eq!(result.time, 0.5);
+    }
+    #[test]
+    fn test_ultra_finishes_on_time_limit() {
+        let mut game = get_game_with_mode(GameMode::Ultra { time_limit: 1.0 });
+        game.update(0.6);
+        assert!(!game.is_finished());
+        game.update(0.6);
+
+        let result = game.result().unwrap();
+        assert!(result.completed);
+        assert_eq!(result.time, 1.0);
+    }
+    #[test]
+    fn test_finished_game_does_not_update() {
+        let mut game = get_game_with_mode(GameMode::Ultra { time_limit: 1.0 });
+        game.update(2.0);
+        let position = game.active.position();
+        game.update(2.0);
+        assert_eq!(game.active.position(), position);
+        assert_eq!(game.get_elapsed_time(), 1.0);
+    }
+    #[test]
+    fn test_game_over_result_is_not_completed() {
+        let mut game = get_game_with_size(6, 10);
+        game.board = game.board.replacing_figure_at_xy(3, 1, Some(FigureType::L));
+        game.board = game.board.replacing_figure_at_xy(4, 1, Some(FigureType::L));
+        game.board = game.board.replacing_figure_at_xy(5, 1, Some(FigureType::L));
+        game.update(10.0);
+        assert!(!game.result().unwrap().completed);
+    }
+    #[test]
+    fn test_pieces_per_second_and_lines_remaining() {
+        let mut game = get_game_with_mode(GameMode::sprint());
+        assert_eq!(game.get_pieces_per_second(), 0.0);
+        game.pieces = 10;
+        game.lines = 12;
+        game.elapsed_time = 4.0;
+        assert_eq!(game.get_pieces_per_second(), 2.5);
+        assert_eq!(game.get_lines_remaining(), Some(28));
+        assert_eq!(get_game().get_lines_remaining(), None);
+    }
+    #[test]
+    fn test_generator_deals_the_figures() {
+        let size = Size {
+            height: 20,
+            width: 10,
+        };
+        let mut generator = SevenBagGenerator::new(5);
+        let first = generator.next_figure().unwrap();
+        let second = generator.next_figure().unwrap();
+        let game = Game::with_generator(
+            &size,
+            get_randomizer(),
+            GameMode::Endless,
+            Box::new(SevenBagGenerator::new(5)),
+        );
+        assert_eq!(game.active.get_type(), first);
+        assert_eq!(game.next.unwrap().get_type(), second);
+    }
+    #[test]
+    fn test_level_follows_mode() {
+        let mut game = get_game_with_mode(GameMode::marathon());
+        assert_eq!(game.get_level(), 1);
+        game.lines = 35;
+        assert_eq!(game.get_level(), 4);
+    }
+
+    #[test]
+    fn test_dig_prefills_garbage() {
+        let mode = GameMode::Dig {
+            garbage_lines: 3,
+            same_column_probability: 1.0,
+        };
+        let game = get_game_with_mode(mode);
+        assert_eq!(game.get_garbage_lines(), 3);
+        assert!(game.board.is_garbage_line(39));
+        assert!(game.board.is_garbage_line(37));
+        assert!(!game.board.is_garbage_line(36));
+        assert_eq!(game.draw_board().len(), 3 * 19);
+    }
+    #[test]
+    fn test_dig_finishes_when_garbage_is_cleared() {
+        let mode = GameMode::Dig {
+            garbage_lines: 1,
+            same_column_probability: 1.0,
+        };
+        let mut game = get_game_with_size_and_mode(4, 4, mode);
+        assert!(game.board.is_garbage_line(3));
+        game.board = game.board.replacing_figure_at_xy(3, 3, Some(FigureType::I));
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        update_loops(&mut game, 2);
+
+        assert_eq!(game.get_garbage_lines(), 0);
+        assert!(game.result().unwrap().completed);
+    }
+
+    #[test]
+    fn test_garbage_is_inserted_after_lock() {
+        let mut game = get_game_with_size(10, 10);
+        game.receive_garbage(2, 0.0);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 8 });
         game.update(10.0);
 
+        assert_eq!(game.get_incoming_garbage(), 0);
+        assert_eq!(game.get_garbage_lines(), 2);
+        assert!(game.board.contains(Point { x: 0, y: 6 }));
+    }
+    #[test]
+    fn test_garbage_waits_for_delay() {
+        let mut game = get_game_with_size(10, 10);
+        game.receive_garbage(2, 15.0);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 8 });
+        game.update(10.0);
+
+        assert_eq!(game.get_incoming_garbage(), 2);
+        assert_eq!(game.get_garbage_lines(), 0);
+    }
+    #[test]
+    fn test_counter_garbage() {
+        let mut game = get_game();
+        game.receive_garbage(1, 0.0);
+        game.receive_garbage(3, 0.0);
+        assert_eq!(game.counter_garbage(2), 0);
+        assert_eq!(game.get_incoming_garbage(), 2);
+        assert_eq!(game.counter_garbage(5), 3);
+        assert_eq!(game.get_incoming_garbage(), 0);
+    }
+    #[test]
+    fn test_line_clear_is_registered() {
+        let mut game = get_game_with_size(2, 2);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        game.update(10.0);
+
+        let line_clear = game.take_line_clear().unwrap();
+        assert_eq!(line_clear.lines, 2);
+        assert_eq!(line_clear.combo, 0);
+        assert!(line_clear.perfect_clear);
+        assert_eq!(game.take_line_clear(), None);
+    }
+    #[test]
+    fn test_combo_and_back_to_back() {
+        let mut game = get_game();
+        game.register_line_clear(4, false, 1);
+        game.register_line_clear(1, false, 1);
+        game.register_line_clear(4, false, 1);
+        game.register_line_clear(4, false, 1);
+
+        let line_clear = game.take_line_clear().unwrap();
+        assert_eq!(line_clear.combo, 3);
+        assert!(line_clear.back_to_back);
+
+        game.register_line_clear(0, false, 1);
+        game.register_line_clear(1, false, 1);
+        assert_eq!(game.take_line_clear().unwrap().combo, 0);
+    }
+    #[test]
+    fn test_t_spin() {
+        let mut game = get_game_with_size(4, 4);
+        game.board = game.board.replacing_figure_at_xy(0, 1, Some(FigureType::I));
+        game.board = game.board.replacing_figure_at_xy(0, 3, Some(FigureType::I));
+        game.board = game.board.replacing_figure_at_xy(2, 3, Some(FigureType::I));
+        game.active = ActiveFigure::new(FigureType::T, Point { x: 0, y: 1 });
+        assert!(!game.is_t_spin());
+        game.rotated_last = true;
+        assert!(game.is_t_spin());
+        game.active = ActiveFigure::new(FigureType::L, Point { x: 0, y: 1 });
+        assert!(!game.is_t_spin());
+    }
+
+    #[test]
+    fn test_puzzle_plays_the_given_figures() {
+        let board = Board::new(&Size {
+            height: 4,
+            width: 4,
+        });
+        let game = Game::puzzle(
+            board,
+            vec![FigureType::S, FigureType::Z],
+            PuzzleGoal::Lines(1),
+        );
+        assert_eq!(game.active.get_type(), FigureType::S);
+        assert_eq!(game.next.unwrap().get_type(), FigureType::Z);
+    }
+    #[test]
+    fn test_puzzle_succeeds_on_goal() {
+        let board = Board::from_rows(vec![
+            vec![None, None, None, None],
+            vec![None, None, None, None],
+            vec![Some(FigureType::J), None, None, Some(FigureType::L)],
+            vec![Some(FigureType::J), None, None, Some(FigureType::L)],
+        ]);
+        let mut game = Game::puzzle(board, vec![FigureType::O], PuzzleGoal::PerfectClear);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 1, y: 0 });
+        update_loops(&mut game, 3);
+
+        let result = game.result().unwrap();
+        assert!(result.completed);
+        assert_eq!(result.lines, 2);
+        assert_eq!(result.pieces, 1);
+    }
+    #[test]
+    fn test_puzzle_fails_when_figures_run_out() {
+        let board = Board::new(&Size {
+            height: 6,
+            width: 4,
+        });
+        let mut game = Game::puzzle(board, vec![FigureType::T], PuzzleGoal::Lines(1));
+        update_loops(&mut game, 10);
+
+        let result = game.result().unwrap();
+        assert!(!result.completed);
+        assert_eq!(result.pieces, 1);
+    }
+    #[test]
+    fn test_puzzle_without_figures_fails() {
+        let board = Board::new(&Size {
+            height: 6,
+            width: 4,
+        });
+        let game = Game::puzzle(board, vec![], PuzzleGoal::Lines(1));
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn test_master_level_advances_per_piece() {
+        let mut game = get_game_with_mode(GameMode::Master);
+        assert_eq!(game.get_level(), 0);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 38 });
+        game.update(0.1);
+        assert_eq!(game.get_pieces(), 0);
+        game.update(0.5);
+
+        assert_eq!(game.get_pieces(), 1);
+        assert_eq!(game.get_level(), 1);
+    }
+    #[test]
+    fn test_master_level_stops_until_line_clear() {
+        let mut game = get_game_with_mode(GameMode::Master);
+        game.master_level = 99;
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 38 });
+        game.update(1.0);
+        assert_eq!(game.get_level(), 99);
+    }
+    #[test]
+    fn test_master_line_clear_advances_per_piece_and_line() {
+        let mut game = get_game_with_mode(GameMode::Master);
+        for y in 38..40 {
+            for x in 2..20 {
+                game.board.set(x, y, Some(FigureType::I)).unwrap();
+            }
+        }
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 38 });
+        game.update(1.0);
+        assert_eq!(game.get_lines(), 2);
+        assert_eq!(game.get_level(), 3);
+    }
+    #[test]
+    fn test_master_waits_are_before_spawn() {
+        let mut game = get_game_with_mode(GameMode::Master);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 38 });
+        game.update(1.0);
+        assert!(game.spawn_timer.is_some());
+        assert!(game.draw_active_figure().is_empty());
+        game.perform(Action::MoveLeft);
+        game.update(0.5);
+
+        assert!(game.spawn_timer.is_none());
+        assert_eq!(game.active.position().y, 0);
+        assert_eq!(game.draw_active_figure().len(), 4);
+    }
+    #[test]
+    fn test_master_lock_delay() {
+        let mut game = get_game_with_mode(GameMode::Master);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 38 });
+        game.update(0.3);
+        game.update(0.1);
+        assert_eq!(game.get_pieces(), 0);
+        game.update(0.1);
+        assert_eq!(game.get_pieces(), 1);
+    }
+    #[test]
+    fn test_master_20g_drops_instantly() {
+        let mut game = get_game_with_mode(GameMode::Master);
+        game.master_level = 500;
+        game.update(0.01);
+        assert_eq!(game.active.bottom_edge(), 39);
+        game.perform(Action::MoveLeft);
+        assert_eq!(game.active.bottom_edge(), 39);
+    }
+    #[test]
+    fn test_master_gravity_is_slow_at_start() {
+        let mut game = get_game_with_mode(GameMode::Master);
+        game.update(1.0);
+        assert_eq!(game.active.position().y, 0);
+        game.update(3.0);
+        assert_eq!(game.active.position().y, 3);
+    }
+
+    #[test]
+    fn test_invisible_stack_is_not_drawn() {
+        let mut game = get_game();
+        game.set_visibility(Visibility::invisible());
+        game.add_active_figure_to_board();
+        assert!(game.draw_board().is_empty());
+        assert_eq!(game.draw().len(), 4);
+    }
+    #[test]
+    fn test_fading_stack_alpha() {
+        let mut game = get_game();
+        game.set_visibility(Visibility::fading(4.0));
+        game.elapsed_time = 2.0;
+        game.add_active_figure_to_board();
+        assert_eq!(game.draw_board()[0].color.alpha, 1.0);
+        game.elapsed_time = 5.0;
+        assert_eq!(game.draw_board()[0].color.alpha, 0.25);
+        game.elapsed_time = 6.0;
+        assert!(game.draw_board().is_empty());
+    }
+    #[test]
+    fn test_garbage_fades_from_when_it_rises() {
+        let mut game = get_game();
+        game.set_visibility(Visibility::fading(4.0));
+        game.elapsed_time = 10.0;
+        game.receive_garbage(1, 0.0);
+        game.insert_ready_garbage();
+        let alphas: Vec<f32> = game
+            .draw_board()
+            .iter()
+            .map(|block| block.color.alpha)
+            .collect();
+        assert_eq!(alphas.len(), game.board.width() - 1);
+        assert!(alphas.iter().all(|alpha| *alpha == 1.0));
+        game.elapsed_time = 13.0;
+        assert_eq!(game.draw_board()[0].color.alpha, 0.25);
+    }
+    #[test]
+    fn test_stack_is_revealed_when_finished() {
+        let mut game = get_game_with_mode(GameMode::Ultra { time_limit: 1.0 });
+        game.set_visibility(Visibility::invisible());
+        game.add_active_figure_to_board();
+        game.update(2.0);
+        assert_eq!(game.draw_board().len(), 4);
+    }
+
+    #[test]
+    fn test_start_point_big_mode() {
+        assert_eq!(Game::figure_start_point(10, 2).x, 2);
+        assert_eq!(Game::figure_start_point(20, 2).x, 6);
+    }
+    #[test]
+    fn test_big_mode_figures() {
+        let mut game = get_game_with_size(20, 10);
+        game.set_big_mode(true);
+        assert!(game.is_big_mode());
+        assert_eq!(game.draw_active_figure().len(), 16);
+        assert_eq!(game.active.position(), Point { x: 2, y: 0 });
+        game.perform(Action::MoveLeft);
+        assert_eq!(game.active.position(), Point { x: 0, y: 0 });
+        game.perform(Action::MoveLeft);
+        assert_eq!(game.active.position(), Point { x: 0, y: 0 });
+    }
+    #[test]
+    fn test_big_mode_line_clear_counts_half() {
+        let mut game = get_game_with_size(4, 4);
+        game.set_big_mode(true);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 }).scaled(2);
+        update_loops(&mut game, 1);
+
+        assert!(game.board.is_empty());
+        assert_eq!(game.get_lines(), 2);
         assert_eq!(game.get_score(), 200);
     }
+    #[test]
+    fn test_big_mode_odd_row_counts_as_line() {
+        let mut game = get_game();
+        game.set_big_mode(true);
+        assert_eq!(game.scaled_lines_count(1), 1);
+        assert_eq!(game.scaled_lines_count(4), 2);
+    }
+
+    #[test]
+    fn test_pentomino_game() {
+        let generator = SevenBagGenerator::with_piece_set(3, PieceSet::pentominoes());
+        let mut game = Game::with_generator(
+            &Size {
+                width: 10,
+                height: 20,
+            },
+            get_randomizer(),
+            GameMode::Endless,
+            Box::new(generator),
+        );
+        for _ in 0..18 {
+            assert_eq!(game.draw_active_figure().len(), 5);
+            game.add_new_active_figure();
+        }
+    }
+    #[test]
+    fn test_spawn_offset() {
+        let pentominoes = PieceSet::pentominoes();
+        let straight = pentominoes.get_figures()[0].clone();
+        let figure = Game::spawned_figure(straight, 10, 1);
+        assert_eq!(figure.position(), Point { x: 3, y: -1 });
+        assert_eq!(figure.to_cartesian()[0], Point { x: 3, y: 1 });
+    }
+
+    #[test]
+    fn test_obstacles_block_figures() {
+        let mut game = get_game_with_size(4, 4);
+        game.board = game
+            .board
+            .replacing_cell_kind_at_xy(0, 3, CellKind::Obstacle);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        update_loops(&mut game, 10);
+
+        assert!(game.board.figure_at_xy(0, 1).is_some());
+        assert!(game.board.figure_at_xy(0, 3).is_none());
+        assert!(
+            game.draw()
+                .iter()
+                .any(|block| block.position() == Point { x: 0, y: 3 }
+                    && block.color == OBSTACLE_COLOR)
+        );
+    }
+    #[test]
+    fn test_masked_cells_complete_lines() {
+        let playable = CellKind::Playable;
+        let masked = CellKind::Masked;
+        let board = Board::with_cell_kinds(vec![
+            vec![playable, playable, playable, playable],
+            vec![playable, playable, playable, playable],
+            vec![masked, playable, playable, masked],
+            vec![masked, playable, playable, masked],
+        ]);
+        let mut game = Game::with_board(
+            board,
+            get_randomizer(),
+            GameMode::Endless,
+            Box::new(SevenBagGenerator::new(1)),
+        );
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 1, y: 0 });
+        update_loops(&mut game, 3);
+
+        assert_eq!(game.get_lines(), 2);
+        assert!(game.board.is_empty());
+        assert_eq!(game.board.cell_kind_at_xy(0, 3), CellKind::Masked);
+    }
+
+    #[test]
+    fn test_wraparound_moves() {
+        let mut game = get_game_with_size(10, 10);
+        game.set_wraparound(true);
+        assert!(game.is_wraparound());
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        game.perform(Action::MoveLeft);
+        assert_eq!(game.active.position(), Point { x: 9, y: 0 });
+        let columns: Vec<i32> = game
+            .draw_active_figure()
+            .iter()
+            .map(|block| block.position().x)
+            .collect();
+        assert_eq!(columns, vec![9, 0, 9, 0]);
+        game.perform(Action::MoveRight);
+        assert_eq!(game.active.position(), Point { x: 0, y: 0 });
+    }
+    #[test]
+    fn test_wraparound_locks_across_the_edge() {
+        let mut game = get_game_with_size(4, 4);
+        game.set_wraparound(true);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 3, y: 2 });
+        game.board = game.board.replacing_figure_at_xy(1, 3, Some(FigureType::I));
+        game.board = game.board.replacing_figure_at_xy(2, 3, Some(FigureType::I));
+        update_loops(&mut game, 1);
+
+        assert_eq!(game.get_lines(), 1);
+        assert!(game.board.figure_at_xy(0, 3).is_some());
+        assert!(game.board.figure_at_xy(3, 3).is_some());
+    }
+
+    #[test]
+    fn test_sticky_gravity_chain_clear() {
+        let mut game = get_game_with_size(5, 4);
+        game.set_line_clear_gravity(LineClearGravity::Sticky);
+        let cells = [
+            (0, 2),
+            (1, 2),
+            (2, 3),
+            (3, 3),
+            (0, 4),
+            (1, 4),
+            (2, 4),
+            (3, 4),
+        ];
+        for (x, y) in cells.iter() {
+            game.board = game
+                .board
+                .replacing_figure_at_xy(*x, *y, Some(FigureType::L));
+        }
+        assert_eq!(game.remove_completed_lines(), vec![1, 1]);
+        assert!(game.board.is_empty());
+    }
+    #[test]
+    fn test_naive_gravity_has_no_chains() {
+        let mut game = get_game_with_size(5, 4);
+        let cells = [
+            (0, 2),
+            (1, 2),
+            (2, 3),
+            (3, 3),
+            (0, 4),
+            (1, 4),
+            (2, 4),
+            (3, 4),
+        ];
+        for (x, y) in cells.iter() {
+            game.board = game
+                .board
+                .replacing_figure_at_xy(*x, *y, Some(FigureType::L));
+        }
+        assert_eq!(game.remove_completed_lines(), vec![1]);
+        assert!(!game.board.is_empty());
+    }
+    #[test]
+    fn test_chain_scoring() {
+        let mut game = get_game();
+        game.add_score_for_chain(&[2, 1]);
+        assert_eq!(game.get_score(), 400);
+        game.register_line_clear(3, false, 2);
+        assert_eq!(game.take_line_clear().unwrap().chain, 2);
+    }
+
+    #[test]
+    fn test_color_match_clears_groups() {
+        let mut game = get_game_with_size(5, 4);
+        game.set_color_match(Some(4));
+        let cells = [(0, 2), (0, 3), (0, 4), (1, 4)];
+        for (x, y) in cells.iter() {
+            game.board = game
+                .board
+                .replacing_figure_at_xy(*x, *y, Some(FigureType::L));
+        }
+        game.board = game.board.replacing_figure_at_xy(0, 1, Some(FigureType::S));
+        assert_eq!(game.remove_completed_lines(), vec![0]);
+        assert_eq!(*game.board.figure_at_xy(0, 4), Some(FigureType::S));
+        assert_eq!(game.get_score(), 40);
+    }
+    #[test]
+    fn test_color_match_chains() {
+        let mut game = get_game_with_size(5, 4);
+        game.set_color_match(Some(3));
+        let cells = [
+            (0, 1, FigureType::J),
+            (0, 2, FigureType::S),
+            (0, 3, FigureType::S),
+            (0, 4, FigureType::S),
+            (1, 4, FigureType::J),
+            (2, 4, FigureType::J),
+        ];
+        for (x, y, figure_type) in cells.iter() {
+            game.board = game
+                .board
+                .replacing_figure_at_xy(*x, *y, Some(figure_type.clone()));
+        }
+        assert_eq!(game.remove_completed_lines(), vec![0, 0]);
+        assert!(game.board.is_empty());
+        assert_eq!(game.get_score(), 30 + 60);
+    }
+
+    #[test]
+    fn test_survival_floor_rises() {
+        let mut game = get_game_with_mode(GameMode::survival());
+        game.update(8.4);
+        assert_eq!(game.get_floor_rise_warning(), None);
+        game.update(0.2);
+        assert!(game.get_floor_rise_warning().is_some());
+        assert_eq!(game.board.garbage_lines_count(), 0);
+        game.update(1.5);
+        assert_eq!(game.board.garbage_lines_count(), 1);
+        assert!(game.board.is_garbage_line(39));
+        assert_eq!(game.get_floor_rise_warning(), None);
+        assert!(game.rise_timer.unwrap() < 10.0);
+    }
+    #[test]
+    fn test_survival_pushes_active_figure_up() {
+        let mut game = get_game_with_size_and_mode(4, 4, GameMode::survival());
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 2 });
+        game.rise_floor();
+        assert_eq!(game.active.position(), Point { x: 0, y: 1 });
+        assert!(!game.is_game_over());
+    }
+    #[test]
+    fn test_survival_tops_out() {
+        let mut game = get_game_with_size_and_mode(4, 4, GameMode::survival());
+        game.board = game.board.replacing_figure_at_xy(3, 2, Some(FigureType::I));
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 2, y: 0 });
+        game.rise_floor();
+        game.rise_floor();
+        assert!(game.is_game_over());
+    }
+    #[test]
+    fn test_survival_long_update_rises_several_rows() {
+        let mut game = get_game_with_mode(GameMode::survival());
+        game.update(20.5);
+        assert_eq!(game.board.garbage_lines_count(), 2);
+        assert!(game.rise_timer.unwrap() > 0.0);
+    }
+    #[test]
+    fn test_survival_topped_out_floor_stops_rising() {
+        let mut game = get_game_with_size_and_mode(4, 4, GameMode::survival());
+        game.board = game.board.replacing_figure_at_xy(0, 0, Some(FigureType::I));
+        game.rise_floor();
+        assert!(game.is_game_over());
+        assert_eq!(game.board.garbage_lines_count(), 0);
+        assert_eq!(*game.board.figure_at_xy(0, 0), Some(FigureType::I));
+    }
+
+    #[test]
+    fn test_reachable_placements() {
+        let game = get_game_with_size(20, 10);
+        let placements = game.reachable_placements();
+        assert_eq!(placements.len(), 34);
+        assert_eq!(placements[0].figure().get_type(), FigureType::T);
+    }
+
+    #[test]
+    fn test_blocked_spawn_is_game_over() {
+        let mut game = get_game_with_size(5, 10);
+        for _ in 0..10 {
+            game.perform(Action::HardDrop);
+        }
+        assert!(game.is_game_over());
+    }
+    #[test]
+    fn test_finesse_faults() {
+        let mut game = get_game_with_size(20, 10);
+        game.set_finesse_tracking(true);
+        game.perform(Action::MoveLeft);
+        game.perform(Action::MoveRight);
+        game.perform(Action::MoveLeft);
+        assert_eq!(game.get_piece_inputs(), 3);
+        game.perform(Action::HardDrop);
+        assert_eq!(game.get_piece_inputs(), 0);
+        assert_eq!(game.get_finesse_faults(), 1);
+        game.perform(Action::MoveRight);
+        game.perform(Action::MoveRight);
+        game.perform(Action::HardDrop);
+        assert_eq!(game.get_finesse_faults(), 1);
+    }
+    #[test]
+    fn test_finesse_tracking_is_off_by_default() {
+        let mut game = get_game_with_size(20, 10);
+        assert!(!game.is_finesse_tracking());
+        game.perform(Action::MoveLeft);
+        game.perform(Action::MoveRight);
+        game.perform(Action::HardDrop);
+        assert_eq!(game.get_finesse_faults(), 0);
+    }
+    #[test]
+    fn test_hard_drop_locks() {
+        let mut game = get_game_with_size(10, 10);
+        game.active = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        game.perform(Action::HardDrop);
+        assert_eq!(game.get_pieces(), 1);
+        assert!(game.board.figure_at_xy(0, 9).is_some());
+        assert_eq!(game.active.position().y, 0);
+    }
+    #[test]
+    fn test_path_to_placement() {
+        let mut game = get_game_with_size(20, 10);
+        let target = game.reachable_placements().pop().unwrap();
+        for action in game.path_to(&target).unwrap() {
+            game.perform(action);
+        }
+        for cell in target.cells() {
+            assert!(game.board.contains(*cell));
+        }
+        assert_eq!(game.get_pieces(), 1);
+    }
 
     // HELPERS
 
@@ -432,12 +1875,16 @@ mod game_tests {
         return get_game_with_size(40, 20);
     }
     fn get_game_with_size(height: usize, width: usize) -> Game {
-        let size = Size {
-            height,
-            width,
-        };
+        let size = Size { height, width };
         return Game::new(&size, get_randomizer());
     }
+    fn get_game_with_mode(mode: GameMode) -> Game {
+        return get_game_with_size_and_mode(40, 20, mode);
+    }
+    fn get_game_with_size_and_mode(height: usize, width: usize, mode: GameMode) -> Game {
+        let size = Size { height, width };
+        return Game::with_mode(&size, get_randomizer(), mode);
+    }
     fn get_randomizer() -> Box<dyn Randomizer> {
         return Box::new(Random { number: 5 });
     }
@@ -0,0 +1,285 @@
+use super::master::MAX_LEVEL;
+use super::Board;
+
+const MARATHON_LINE_GOAL: u32 = 150;
+const MARATHON_LEVEL_CAP: u32 = 15;
+const SPRINT_LINE_GOAL: u32 = 40;
+const ULTRA_TIME_LIMIT: f64 = 180.0; //secs
+const LINES_PER_LEVEL: u32 = 10;
+const DIG_GARBAGE_LINES: usize = 10;
+const DIG_SAME_COLUMN_PROBABILITY: f64 = 0.3;
+const SURVIVAL_RISE_INTERVAL: f64 = 10.0; //secs
+const SURVIVAL_MIN_RISE_INTERVAL: f64 = 1.0; //secs
+const SURVIVAL_ACCELERATION: f64 = 0.8; // interval factor per minute
+const SURVIVAL_WARNING_TIME: f64 = 1.5; //secs
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum GameMode {
+    #[default]
+    Endless,
+    Marathon {
+        line_goal: u32,
+        level_cap: u32,
+    },
+    Sprint {
+        line_goal: u32,
+    },
+    Ultra {
+        time_limit: f64,
+    },
+    Dig {
+        garbage_lines: usize,
+        same_column_probability: f64,
+    },
+    Puzzle {
+        goal: PuzzleGoal,
+    },
+    Master,
+    // The floor rises one garbage row every interval. The interval shrinks by
+    // the `acceleration` factor every minute, down to `min_rise_interval`.
+    Survival {
+        rise_interval: f64,
+        min_rise_interval: f64,
+        acceleration: f64,
+        warning_time: f64,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PuzzleGoal {
+    PerfectClear,
+    Lines(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult {
+    pub mode: GameMode,
+    pub completed: bool,
+    pub time: f64,
+    pub lines: u32,
+    pub score: u64,
+    pub pieces: u32,
+}
+
+impl GameMode {
+    pub fn marathon() -> GameMode {
+        return GameMode::Marathon {
+            line_goal: MARATHON_LINE_GOAL,
+            level_cap: MARATHON_LEVEL_CAP,
+        };
+    }
+
+    pub fn sprint() -> GameMode {
+        return GameMode::Sprint {
+            line_goal: SPRINT_LINE_GOAL,
+        };
+    }
+
+    pub fn ultra() -> GameMode {
+        return GameMode::Ultra {
+            time_limit: ULTRA_TIME_LIMIT,
+        };
+    }
+
+    pub fn dig() -> GameMode {
+        return GameMode::Dig {
+            garbage_lines: DIG_GARBAGE_LINES,
+            same_column_probability: DIG_SAME_COLUMN_PROBABILITY,
+        };
+    }
+
+    pub fn survival() -> GameMode {
+        return GameMode::Survival {
+            rise_interval: SURVIVAL_RISE_INTERVAL,
+            min_rise_interval: SURVIVAL_MIN_RISE_INTERVAL,
+            acceleration: SURVIVAL_ACCELERATION,
+            warning_time: SURVIVAL_WARNING_TIME,
+        };
+    }
+
+    pub fn level_for(&self, lines: u32) -> u32 {
+        let level = lines / LINES_PER_LEVEL + 1;
+        return match self {
+            GameMode::Marathon { level_cap, .. } => level.min(*level_cap),
+            _ => level,
+        };
+    }
+
+    pub fn line_goal(&self) -> Option<u32> {
+        return match self {
+            GameMode::Marathon { line_goal, .. } => Some(*line_goal),
+            GameMode::Sprint { line_goal } => Some(*line_goal),
+            _ => None,
+        };
+    }
+
+    pub fn time_limit(&self) -> Option<f64> {
+        return match self {
+            GameMode::Ultra { time_limit } => Some(*time_limit),
+            _ => None,
+        };
+    }
+
+    pub fn is_completed(&self, lines: u32, level: u32, time: f64, board: &Board) -> bool {
+        return match self {
+            GameMode::Endless => false,
+            GameMode::Marathon { line_goal, .. } => lines >= *line_goal,
+            GameMode::Sprint { line_goal } => lines >= *line_goal,
+            GameMode::Ultra { time_limit } => time >= *time_limit,
+            GameMode::Dig { .. } => board.garbage_lines_count() == 0,
+            GameMode::Puzzle { goal } => match goal {
+                PuzzleGoal::PerfectClear => lines > 0 && board.is_empty(),
+                PuzzleGoal::Lines(line_goal) => lines >= *line_goal,
+            },
+            GameMode::Master => level >= MAX_LEVEL,
+            GameMode::Survival { .. } => false,
+        };
+    }
+
+    // Seconds until the next floor rise, for a rise happening at `time`.
+    pub fn rise_interval(&self, time: f64) -> Option<f64> {
+        return match self {
+            GameMode::Survival {
+                rise_interval,
+                min_rise_interval,
+                acceleration,
+                ..
+            } => {
+                let interval = rise_interval * acceleration.powf(time / 60.0);
+                Some(interval.max(*min_rise_interval))
+            }
+            _ => None,
+        };
+    }
+
+    pub fn rise_warning_time(&self) -> Option<f64> {
+        return match self {
+            GameMode::Survival { warning_time, .. } => Some(*warning_time),
+            _ => None,
+        };
+    }
+
+    pub(crate) fn same_column_probability(&self) -> f64 {
+        return match self {
+            GameMode::Dig {
+                same_column_probability,
+                ..
+            } => *same_column_probability,
+            _ => DIG_SAME_COLUMN_PROBABILITY,
+        };
+    }
+
+    pub(crate) fn initial_garbage_lines(&self) -> usize {
+        return match self {
+            GameMode::Dig { garbage_lines, .. } => *garbage_lines,
+            _ => 0,
+        };
+    }
+
+    // Seconds per row. Marathon follows the guideline gravity curve,
+    // the other modes keep the constant classic speed.
+    pub(crate) fn moving_period(&self, level: u32, classic_period: f64) -> f64 {
+        return match self {
+            GameMode::Marathon { .. } => {
+                let steps = (level - 1) as f64;
+                (0.8 - steps * 0.007).powf(steps)
+            }
+            _ => classic_period,
+        };
+    }
+}
+
+#[cfg(test)]
+mod game_mode_tests {
+    use super::super::{FigureType, Size};
+    use super::*;
+
+    fn board() -> Board {
+        return Board::new(&Size {
+            height: 4,
+            width: 4,
+        });
+    }
+
+    #[test]
+    fn test_marathon_level_is_capped() {
+        let mode = GameMode::marathon();
+        assert_eq!(mode.level_for(0), 1);
+        assert_eq!(mode.level_for(19), 2);
+        assert_eq!(mode.level_for(149), 15);
+        assert_eq!(mode.level_for(500), 15);
+    }
+    #[test]
+    fn test_endless_level_is_not_capped() {
+        assert_eq!(GameMode::Endless.level_for(500), 51);
+    }
+    #[test]
+    fn test_marathon_completion() {
+        let mode = GameMode::marathon();
+        assert!(!mode.is_completed(149, 1, 1000.0, &board()));
+        assert!(mode.is_completed(150, 1, 0.0, &board()));
+    }
+    #[test]
+    fn test_sprint_completion() {
+        let mode = GameMode::sprint();
+        assert!(!mode.is_completed(39, 1, 0.0, &board()));
+        assert!(mode.is_completed(40, 1, 0.0, &board()));
+        assert_eq!(mode.line_goal(), Some(40));
+        assert_eq!(GameMode::ultra().line_goal(), None);
+    }
+    #[test]
+    fn test_ultra_completion() {
+        let mode = GameMode::ultra();
+        assert!(!mode.is_completed(1000, 1, 179.0, &board()));
+        assert!(mode.is_completed(0, 1, 180.0, &board()));
+        assert_eq!(mode.time_limit(), Some(180.0));
+    }
+    #[test]
+    fn test_dig_completion() {
+        let mode = GameMode::dig();
+        let garbage_board = board().inserting_garbage_lines(&[0]);
+        assert!(!mode.is_completed(100, 1, 0.0, &garbage_board));
+        assert!(mode.is_completed(0, 1, 0.0, &board()));
+        assert_eq!(mode.initial_garbage_lines(), 10);
+    }
+    #[test]
+    fn test_puzzle_perfect_clear_completion() {
+        let mode = GameMode::Puzzle {
+            goal: PuzzleGoal::PerfectClear,
+        };
+        let filled_board = board().replacing_figure_at_xy(0, 3, Some(FigureType::I));
+        assert!(!mode.is_completed(0, 1, 0.0, &board()));
+        assert!(!mode.is_completed(2, 1, 0.0, &filled_board));
+        assert!(mode.is_completed(2, 1, 0.0, &board()));
+    }
+    #[test]
+    fn test_puzzle_lines_completion() {
+        let mode = GameMode::Puzzle {
+            goal: PuzzleGoal::Lines(3),
+        };
+        assert!(!mode.is_completed(2, 1, 0.0, &board()));
+        assert!(mode.is_completed(3, 1, 0.0, &board()));
+    }
+    #[test]
+    fn test_master_completion() {
+        assert!(!GameMode::Master.is_completed(0, 998, 0.0, &board()));
+        assert!(GameMode::Master.is_completed(0, 999, 0.0, &board()));
+    }
+    #[test]
+    fn test_survival_rises_faster() {
+        let mode = GameMode::survival();
+        assert_eq!(mode.rise_interval(0.0), Some(10.0));
+        assert_eq!(mode.rise_interval(60.0), Some(8.0));
+        assert_eq!(mode.rise_interval(6000.0), Some(1.0));
+        assert_eq!(mode.rise_warning_time(), Some(1.5));
+        assert_eq!(GameMode::Endless.rise_interval(0.0), None);
+        assert!(!mode.is_completed(1000, 1, 1000.0, &board()));
+    }
+    #[test]
+    fn test_marathon_gets_faster() {
+        let mode = GameMode::marathon();
+        assert_eq!(mode.moving_period(1, 0.2), 1.0);
+        assert!(mode.moving_period(2, 0.2) < mode.moving_period(1, 0.2));
+        assert_eq!(GameMode::sprint().moving_period(10, 0.2), 0.2);
+    }
+}
@@ -0,0 +1,126 @@
+use super::Randomizer;
+
+const PERCENT: i32 = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GarbageGenerator {
+    same_column_probability: f64,
+    last_hole: Option<usize>,
+}
+
+impl GarbageGenerator {
+    pub fn new(same_column_probability: f64) -> GarbageGenerator {
+        return GarbageGenerator {
+            same_column_probability,
+            last_hole: None,
+        };
+    }
+
+    pub fn next_holes(
+        &mut self,
+        count: usize,
+        width: usize,
+        randomizer: &dyn Randomizer,
+    ) -> Vec<usize> {
+        // There's no column for a hole on a board without columns.
+        if width == 0 {
+            return vec![];
+        }
+        return (0..count)
+            .map(|_| self.next_hole(width, randomizer))
+            .collect();
+    }
+
+    fn next_hole(&mut self, width: usize, randomizer: &dyn Randomizer) -> usize {
+        let hole = match self.last_hole {
+            Some(last_hole) if last_hole < width => {
+                if self.keeps_same_column(randomizer) {
+                    last_hole
+                } else {
+                    GarbageGenerator::different_column(last_hole, width, randomizer)
+                }
+            }
+            _ => GarbageGenerator::random_column(width, randomizer),
+        };
+        self.last_hole = Some(hole);
+        return hole;
+    }
+
+    fn keeps_same_column(&self, randomizer: &dyn Randomizer) -> bool {
+        let roll = randomizer.random_between(0, PERCENT - 1);
+        return (roll as f64) < self.same_column_probability * PERCENT as f64;
+    }
+
+    fn random_column(width: usize, randomizer: &dyn Randomizer) -> usize {
+        let column = randomizer.random_between(0, width as i32 - 1);
+        return (column.max(0) as usize).min(width - 1);
+    }
+
+    fn different_column(last_hole: usize, width: usize, randomizer: &dyn Randomizer) -> usize {
+        if width < 2 {
+            return last_hole;
+        }
+        let column = GarbageGenerator::random_column(width - 1, randomizer);
+        if column >= last_hole {
+            return column + 1;
+        }
+        return column;
+    }
+}
+
+#[cfg(test)]
+mod garbage_tests {
+    use super::*;
+    use std::cell::Cell;
+
+    struct Sequence {
+        numbers: Vec<i32>,
+        index: Cell<usize>,
+    }
+
+    impl Randomizer for Sequence {
+        fn random_between(&self, _first: i32, _last: i32) -> i32 {
+            let index = self.index.get();
+            self.index.set(index + 1);
+            return self.numbers[index % self.numbers.len()];
+        }
+    }
+
+    fn sequence(numbers: Vec<i32>) -> Sequence {
+        return Sequence {
+            numbers,
+            index: Cell::new(0),
+        };
+    }
+
+    #[test]
+    fn test_first_hole_is_random() {
+        let mut generator = GarbageGenerator::new(0.0);
+        let holes = generator.next_holes(1, 10, &sequence(vec![7]));
+        assert_eq!(holes, vec![7]);
+    }
+    #[test]
+    fn test_hole_is_kept_in_same_column() {
+        let mut generator = GarbageGenerator::new(1.0);
+        let holes = generator.next_holes(3, 10, &sequence(vec![4, 50, 50]));
+        assert_eq!(holes, vec![4, 4, 4]);
+    }
+    #[test]
+    fn test_hole_changes_column() {
+        let mut generator = GarbageGenerator::new(0.0);
+        let holes = generator.next_holes(3, 10, &sequence(vec![4, 0, 4]));
+        assert_eq!(holes, vec![4, 5, 0]);
+    }
+    #[test]
+    fn test_hole_is_inside_the_board() {
+        let mut generator = GarbageGenerator::new(0.0);
+        let holes = generator.next_holes(1, 4, &sequence(vec![100]));
+        assert_eq!(holes, vec![3]);
+    }
+    #[test]
+    fn test_no_holes_without_columns() {
+        let mut generator = GarbageGenerator::new(0.5);
+        let holes = generator.next_holes(2, 0, &sequence(vec![0]));
+        assert!(holes.is_empty());
+    }
+}
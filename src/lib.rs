@@ -1,15 +1,43 @@
+#![allow(clippy::needless_return)]
+
 mod active_figure;
+mod ai;
+mod attack;
 mod board;
+mod env;
 mod figure;
+mod finesse;
 mod game;
+mod game_mode;
+mod garbage;
+mod line_clear;
+mod master;
 mod move_validator;
+mod perfect_clear;
+mod piece_generator;
+mod placement;
+mod seeded_randomizer;
+mod versus;
+mod visibility;
 
-use active_figure::ActiveFigure;
-use board::Board;
-use figure::{block, geometry, graphics, Figure, FigureType, Matrix};
-use geometry::Point;
-use graphics::Color;
+use figure::{block, geometry, graphics, Figure};
+pub use graphics::Color;
 
+pub use active_figure::ActiveFigure;
+pub use ai::{Ai, Weights};
+pub use attack::AttackTable;
 pub use block::Block;
-pub use game::{Game, Randomizer, Action};
-pub use geometry::Size;
+pub use board::{Board, CellKind, TooManyFigureTypes};
+pub use env::{ActionSpace, Environment, Observation, ObservationEncoding, Rewards, StepInfo};
+pub use figure::{FigureType, PieceDefinition, PieceParseError, PieceSet};
+pub use finesse::minimal_inputs;
+pub use game::{Action, Game, Randomizer};
+pub use game_mode::{GameMode, GameResult, PuzzleGoal};
+pub use geometry::{Point, Size};
+pub use line_clear::{LineClear, LineClearGravity};
+pub use perfect_clear::PerfectClearSolver;
+pub use piece_generator::{PieceGenerator, QueueGenerator, RandomGenerator, SevenBagGenerator};
+pub use placement::{reachable_placements, Placement};
+pub use seeded_randomizer::SeededRandomizer;
+pub use versus::{Match, Standing};
+pub use visibility::{RevealFlash, StackVisibility, Visibility};
@@ -0,0 +1,64 @@
+const TETRIS_LINES: u32 = 4;
+
+// How the blocks above fall after clearing lines:
+// - Naive: the rows above shift down as a unit.
+// - Sticky: every connected chunk of blocks falls on its own.
+// - Cascade: like sticky, but only blocks of the same figure type stick together.
+// With sticky and cascade gravity the falling chunks can complete new lines,
+// which are cleared as a chain.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LineClearGravity {
+    #[default]
+    Naive,
+    Sticky,
+    Cascade,
+}
+
+// `chain` counts the clears triggered by a single figure, 1 without chain clears.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineClear {
+    pub lines: u32,
+    pub t_spin: bool,
+    pub combo: u32,
+    pub back_to_back: bool,
+    pub perfect_clear: bool,
+    pub chain: u32,
+}
+
+impl LineClear {
+    pub fn is_tetris(&self) -> bool {
+        return self.lines >= TETRIS_LINES;
+    }
+
+    // Clears that keep the back to back chain alive.
+    pub fn is_difficult(&self) -> bool {
+        return self.is_tetris() || (self.t_spin && self.lines > 0);
+    }
+}
+
+#[cfg(test)]
+mod line_clear_tests {
+    use super::*;
+
+    fn clear(lines: u32, t_spin: bool) -> LineClear {
+        return LineClear {
+            lines,
+            t_spin,
+            combo: 0,
+            back_to_back: false,
+            perfect_clear: false,
+            chain: 1,
+        };
+    }
+
+    #[test]
+    fn test_tetris_is_difficult() {
+        assert!(clear(4, false).is_difficult());
+        assert!(!clear(3, false).is_difficult());
+    }
+    #[test]
+    fn test_t_spin_is_difficult() {
+        assert!(clear(1, true).is_difficult());
+        assert!(!clear(0, true).is_difficult());
+    }
+}
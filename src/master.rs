@@ -0,0 +1,144 @@
+// Based on TGM Master mode: https://tetris.wiki/Tetris_The_Grand_Master_2
+
+pub const MAX_LEVEL: u32 = 999;
+const FRAMES_PER_SECOND: f64 = 60.0;
+const INSTANT_GRAVITY: f64 = 20.0;
+
+// Gravity in 1/256 rows per frame, from the level it applies.
+const GRAVITY_TABLE: [(u32, u32); 30] = [
+    (0, 4),
+    (30, 6),
+    (35, 8),
+    (40, 10),
+    (50, 12),
+    (60, 16),
+    (70, 32),
+    (80, 48),
+    (90, 64),
+    (100, 80),
+    (120, 96),
+    (140, 112),
+    (160, 128),
+    (170, 144),
+    (200, 4),
+    (220, 32),
+    (230, 64),
+    (233, 96),
+    (236, 128),
+    (239, 160),
+    (243, 192),
+    (247, 224),
+    (251, 256),
+    (300, 512),
+    (330, 768),
+    (360, 1024),
+    (400, 1280),
+    (420, 1024),
+    (450, 768),
+    (500, 5120),
+];
+
+// ARE, lock delay and line clear delay in frames, from the level they apply.
+const DELAY_TABLE: [(u32, u32, u32, u32); 6] = [
+    (0, 25, 30, 40),
+    (500, 25, 30, 25),
+    (600, 25, 30, 16),
+    (700, 16, 30, 12),
+    (800, 12, 30, 6),
+    (900, 12, 17, 6),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasterTiming {
+    pub gravity: f64, // rows per frame
+    pub are: f64,
+    pub lock_delay: f64,
+    pub line_clear_delay: f64,
+}
+
+impl MasterTiming {
+    pub fn for_level(level: u32) -> MasterTiming {
+        let gravity = GRAVITY_TABLE
+            .iter()
+            .rev()
+            .find(|(from_level, _)| level >= *from_level)
+            .map_or(4, |(_, gravity)| *gravity);
+        let (_, are, lock_delay, line_clear_delay) = DELAY_TABLE
+            .iter()
+            .rev()
+            .find(|(from_level, _, _, _)| level >= *from_level)
+            .cloned()
+            .unwrap_or(DELAY_TABLE[0]);
+        return MasterTiming {
+            gravity: gravity as f64 / 256.0,
+            are: are as f64 / FRAMES_PER_SECOND,
+            lock_delay: lock_delay as f64 / FRAMES_PER_SECOND,
+            line_clear_delay: line_clear_delay as f64 / FRAMES_PER_SECOND,
+        };
+    }
+
+    pub fn is_instant_gravity(&self) -> bool {
+        return self.gravity >= INSTANT_GRAVITY;
+    }
+
+    pub fn rows_for(&self, delta_time: f64) -> f64 {
+        return self.gravity * FRAMES_PER_SECOND * delta_time;
+    }
+}
+
+// Every placed figure and every cleared line advance the level. Placing a
+// figure without clearing lines does not advance it at x99 (and 998).
+pub fn is_level_stop(level: u32) -> bool {
+    return level % 100 == 99 || level == MAX_LEVEL - 1;
+}
+
+pub fn level_after_lock(level: u32, lines: u32) -> u32 {
+    if lines > 0 {
+        return (level + 1 + lines).min(MAX_LEVEL);
+    }
+    if is_level_stop(level) {
+        return level;
+    }
+    return (level + 1).min(MAX_LEVEL);
+}
+
+#[cfg(test)]
+mod master_tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_starts_slow() {
+        let timing = MasterTiming::for_level(0);
+        assert_eq!(timing.gravity, 4.0 / 256.0);
+        assert_eq!(timing.are, 25.0 / 60.0);
+        assert_eq!(timing.lock_delay, 0.5);
+        assert!(!timing.is_instant_gravity());
+    }
+    #[test]
+    fn test_timing_reaches_20g() {
+        let timing = MasterTiming::for_level(500);
+        assert!(timing.is_instant_gravity());
+        assert_eq!(timing.line_clear_delay, 25.0 / 60.0);
+    }
+    #[test]
+    fn test_delays_shrink() {
+        let start = MasterTiming::for_level(0);
+        let end = MasterTiming::for_level(950);
+        assert!(end.are < start.are);
+        assert!(end.lock_delay < start.lock_delay);
+        assert!(end.line_clear_delay < start.line_clear_delay);
+    }
+    #[test]
+    fn test_level_stop() {
+        assert_eq!(level_after_lock(98, 0), 99);
+        assert_eq!(level_after_lock(99, 0), 99);
+        assert_eq!(level_after_lock(99, 1), 101);
+        assert_eq!(level_after_lock(998, 0), 998);
+    }
+    #[test]
+    fn test_lines_advance_level() {
+        assert_eq!(level_after_lock(10, 4), 15);
+        assert_eq!(level_after_lock(0, 1), 2);
+        assert_eq!(level_after_lock(997, 4), MAX_LEVEL);
+    }
+}
@@ -11,19 +11,16 @@ pub fn can_move_down(figure: &ActiveFigure, board: &Board) -> bool {
 }
 
 fn will_colide_with_block(figure: &ActiveFigure, board: &Board) -> bool {
-    let points = figure.to_cartesian();
-    for point in points {
-        if board.contains(point) {
-            return true;
-        }
-    }
-    return false;
+    return figure.cells().any(|point| board.contains(point));
 }
 
 fn will_collide_with_edge(active_figure: &ActiveFigure, board: &Board) -> bool {
+    let collided_with_bottom = active_figure.bottom_edge() >= board.height() as i32;
+    if board.is_wrapping_around() {
+        return collided_with_bottom;
+    }
     let collided_with_left = active_figure.left_edge() < 0;
     let collided_with_right = active_figure.right_edge() >= board.width() as i32;
-    let collided_with_bottom = active_figure.bottom_edge() >= board.height() as i32;
     return collided_with_left || collided_with_right || collided_with_bottom;
 }
 
@@ -33,10 +30,9 @@ fn is_at_the_bottom(figure: &ActiveFigure, board: &Board) -> bool {
 
 #[cfg(test)]
 mod move_validator_tests {
+    use super::super::geometry::{Point, Size};
     use super::super::FigureType;
     use super::*;
-    use super::super::geometry::{Point, Size};
-    use ActiveFigure;
 
     #[test]
     fn test_is_at_the_bottom() {
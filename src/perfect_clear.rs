@@ -0,0 +1,276 @@
+use super::placement::reachable_placements;
+use super::{Board, FigureType, Game, Placement};
+use std::collections::HashSet;
+
+// Filled cells of the lines left, next figure of the queue and held figure.
+type SearchKey = (Vec<bool>, usize, Option<String>);
+
+// Searches the placements clearing the whole board using the figures of a
+// known queue, without going above the lowest `max_lines` lines. Figures
+// spawn at the top of the board and move with the real movement rules, so
+// the board must be as tall as the game board. With hold, the first figure
+// put on hold is taken from the queue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerfectClearSolver {
+    max_lines: usize,
+    hold: bool,
+}
+
+impl PerfectClearSolver {
+    pub fn new(max_lines: usize) -> PerfectClearSolver {
+        return PerfectClearSolver {
+            max_lines,
+            hold: false,
+        };
+    }
+
+    pub fn holding(&self, hold: bool) -> PerfectClearSolver {
+        return PerfectClearSolver {
+            max_lines: self.max_lines,
+            hold,
+        };
+    }
+
+    pub fn first_solution(&self, board: &Board, queue: &[FigureType]) -> Option<Vec<Placement>> {
+        let mut search = Search::new(self, queue, true);
+        search.solve(board);
+        return search.solutions.pop();
+    }
+
+    // Every sequence of placements reaching a perfect clear. Sequences placing
+    // the same figures in a different order are different solutions.
+    pub fn all_solutions(&self, board: &Board, queue: &[FigureType]) -> Vec<Vec<Placement>> {
+        let mut search = Search::new(self, queue, false);
+        search.solve(board);
+        return search.solutions;
+    }
+}
+
+struct Search<'a> {
+    solver: &'a PerfectClearSolver,
+    queue: &'a [FigureType],
+    first_only: bool,
+    path: Vec<Placement>,
+    solutions: Vec<Vec<Placement>>,
+    dead_ends: HashSet<SearchKey>,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        solver: &'a PerfectClearSolver,
+        queue: &'a [FigureType],
+        first_only: bool,
+    ) -> Search<'a> {
+        return Search {
+            solver,
+            queue,
+            first_only,
+            path: vec![],
+            solutions: vec![],
+            dead_ends: HashSet::new(),
+        };
+    }
+
+    fn solve(&mut self, board: &Board) {
+        let lines = self.solver.max_lines.min(board.height());
+        let top = board.height() - lines;
+        if (0..top).all(|line| board.is_line_empty(line)) {
+            self.search(board, lines, 0, None);
+        }
+    }
+
+    // Returns whether a solution was found from this state.
+    fn search(
+        &mut self,
+        board: &Board,
+        lines: usize,
+        index: usize,
+        hold: Option<FigureType>,
+    ) -> bool {
+        if board.is_empty() {
+            self.solutions.push(self.path.clone());
+            return true;
+        }
+        if !self.has_enough_blocks(board, lines, index, &hold) {
+            return false;
+        }
+        let key = search_key(board, lines, index, &hold);
+        if self.dead_ends.contains(&key) {
+            return false;
+        }
+        let top = (board.height() - lines) as i32;
+        let mut found = false;
+        for (figure_type, next_index, next_hold) in self.choices(index, &hold) {
+            let figure = Game::spawned_figure(figure_type, board.width(), 1);
+            for placement in reachable_placements(board, &figure) {
+                if placement.cells().iter().any(|cell| cell.y < top) {
+                    continue;
+                }
+                let mut placed = placement.locked_on(board);
+                let completed: Vec<usize> = (0..placed.height())
+                    .filter(|line| placed.is_line_complete(*line))
+                    .collect();
+                placed.clear_rows(&completed);
+                self.path.push(placement);
+                let solved = self.search(
+                    &placed,
+                    lines - completed.len(),
+                    next_index,
+                    next_hold.clone(),
+                );
+                self.path.pop();
+                found = found || solved;
+                if found && self.first_only {
+                    return true;
+                }
+            }
+        }
+        if !found {
+            self.dead_ends.insert(key);
+        }
+        return found;
+    }
+
+    // Figure to place, next queue index and held figure after placing it.
+    fn choices(
+        &self,
+        index: usize,
+        hold: &Option<FigureType>,
+    ) -> Vec<(FigureType, usize, Option<FigureType>)> {
+        let mut choices = vec![];
+        let current = self.queue.get(index).cloned();
+        if let Some(current) = current.clone() {
+            choices.push((current, index + 1, hold.clone()));
+        }
+        if !self.solver.hold {
+            return choices;
+        }
+        match (hold, current) {
+            (Some(held), current) if current.as_ref() != Some(held) => {
+                choices.push((held.clone(), index + 1, current));
+            }
+            (None, Some(current)) => {
+                if let Some(next) = self.queue.get(index + 1) {
+                    choices.push((next.clone(), index + 2, Some(current)));
+                }
+            }
+            _ => {}
+        }
+        return choices;
+    }
+
+    // The figures left must have enough blocks to fill the lines left.
+    fn has_enough_blocks(
+        &self,
+        board: &Board,
+        lines: usize,
+        index: usize,
+        hold: &Option<FigureType>,
+    ) -> bool {
+        let top = board.height() - lines;
+        let empty_cells = (top..board.height())
+            .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| {
+                board.figure_at_xy(*x, *y).is_none() && board.is_playable_at_xy(*x, *y)
+            })
+            .count();
+        let blocks: usize = self.queue[index.min(self.queue.len())..]
+            .iter()
+            .chain(hold)
+            .map(|figure_type| figure_type.definition().blocks_count())
+            .sum();
+        return empty_cells <= blocks;
+    }
+}
+
+fn search_key(board: &Board, lines: usize, index: usize, hold: &Option<FigureType>) -> SearchKey {
+    let cells = (board.height() - lines..board.height())
+        .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+        .map(|(x, y)| board.figure_at_xy(x, y).is_some())
+        .collect();
+    return (
+        cells,
+        index,
+        hold.as_ref().map(|figure_type| figure_type.name()),
+    );
+}
+
+#[cfg(test)]
+mod perfect_clear_tests {
+    use super::super::Point;
+    use super::*;
+
+    fn get_board(height: usize, rows: &[&str]) -> Board {
+        let width = rows[0].len();
+        let mut cells = vec![vec![None; width]; height - rows.len()];
+        for row in rows {
+            cells.push(
+                row.chars()
+                    .map(|cell| {
+                        if cell == 'X' {
+                            Some(FigureType::I)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect(),
+            );
+        }
+        return Board::from_rows(cells);
+    }
+
+    #[test]
+    fn test_single_figure_perfect_clear() {
+        let board = get_board(6, &["XX..", "XX.."]);
+        let solution = PerfectClearSolver::new(2)
+            .first_solution(&board, &[FigureType::O])
+            .unwrap();
+        assert_eq!(solution.len(), 1);
+        assert!(solution[0].cells().contains(&Point { x: 3, y: 5 }));
+    }
+    #[test]
+    fn test_no_perfect_clear() {
+        let board = get_board(6, &["XX..", "XX.."]);
+        let solver = PerfectClearSolver::new(2);
+        assert_eq!(solver.first_solution(&board, &[FigureType::I]), None);
+        assert!(solver.all_solutions(&board, &[FigureType::I]).is_empty());
+    }
+    #[test]
+    fn test_empty_board_is_solved() {
+        let board = get_board(6, &["...."]);
+        let solution = PerfectClearSolver::new(2).first_solution(&board, &[]);
+        assert_eq!(solution, Some(vec![]));
+    }
+    #[test]
+    fn test_stack_above_the_lines_has_no_solution() {
+        let board = get_board(6, &["XX..", "XX..", "XX.."]);
+        let solver = PerfectClearSolver::new(2);
+        assert_eq!(solver.first_solution(&board, &[FigureType::O]), None);
+    }
+    #[test]
+    fn test_solution_with_hold() {
+        let board = get_board(6, &["XX..", "XX.."]);
+        let queue = [FigureType::T, FigureType::O];
+        let solver = PerfectClearSolver::new(2);
+        assert_eq!(solver.first_solution(&board, &queue), None);
+        let solution = solver.holding(true).first_solution(&board, &queue).unwrap();
+        assert_eq!(solution.len(), 1);
+        assert_eq!(solution[0].figure().get_type(), FigureType::O);
+    }
+    #[test]
+    fn test_all_solutions() {
+        let board = get_board(20, &["XX........"; 2]);
+        let queue = vec![FigureType::O; 4];
+        let solutions = PerfectClearSolver::new(2).all_solutions(&board, &queue);
+        // Every order of the four column pairs.
+        assert_eq!(solutions.len(), 24);
+        assert!(solutions.iter().all(|solution| solution.len() == 4));
+    }
+    #[test]
+    fn test_two_line_perfect_clear() {
+        let board = get_board(20, &["XXXXXX....", "XXXXXX...."]);
+        let queue = [FigureType::L, FigureType::L];
+        let solution = PerfectClearSolver::new(2).first_solution(&board, &queue);
+        assert_eq!(solution.map(|solution| solution.len()), Some(2));
+    }
+}
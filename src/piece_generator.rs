@@ -0,0 +1,185 @@
+use super::{FigureType, PieceSet, Randomizer, SeededRandomizer};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+// Returning `None` means there are no figures left to play.
+pub trait PieceGenerator {
+    fn next_figure(&mut self) -> Option<FigureType>;
+}
+
+// Picks every figure independently with the game randomizer.
+pub struct RandomGenerator {
+    randomizer: Rc<dyn Randomizer>,
+    piece_set: PieceSet,
+}
+
+impl RandomGenerator {
+    pub fn new(randomizer: Rc<dyn Randomizer>) -> RandomGenerator {
+        return RandomGenerator::with_piece_set(randomizer, PieceSet::tetrominoes());
+    }
+
+    pub fn with_piece_set(randomizer: Rc<dyn Randomizer>, piece_set: PieceSet) -> RandomGenerator {
+        return RandomGenerator {
+            randomizer,
+            piece_set,
+        };
+    }
+}
+
+impl PieceGenerator for RandomGenerator {
+    fn next_figure(&mut self) -> Option<FigureType> {
+        let figures = self.piece_set.get_figures();
+        let last = figures.len() as i32 - 1;
+        let index = self
+            .randomizer
+            .random_between(0, last)
+            .clamp(0, last.max(0));
+        return figures.get(index as usize).cloned();
+    }
+}
+
+// Deals every figure of the piece set (the seven tetrominoes by default)
+// in a shuffled bag, refilled when empty.
+// Clones produce the same sequence, so every player of a race gets the same figures.
+#[derive(Debug, Clone)]
+pub struct SevenBagGenerator {
+    randomizer: SeededRandomizer,
+    piece_set: PieceSet,
+    bag: Vec<FigureType>,
+}
+
+impl SevenBagGenerator {
+    pub fn new(seed: u64) -> SevenBagGenerator {
+        return SevenBagGenerator::with_piece_set(seed, PieceSet::tetrominoes());
+    }
+
+    pub fn with_piece_set(seed: u64, piece_set: PieceSet) -> SevenBagGenerator {
+        return SevenBagGenerator {
+            randomizer: SeededRandomizer::new(seed),
+            piece_set,
+            bag: vec![],
+        };
+    }
+
+    fn refill_bag(&mut self) {
+        let mut bag = self.piece_set.get_figures().to_vec();
+        for index in (1..bag.len()).rev() {
+            let other = self.randomizer.random_between(0, index as i32) as usize;
+            bag.swap(index, other);
+        }
+        self.bag = bag;
+    }
+}
+
+impl PieceGenerator for SevenBagGenerator {
+    fn next_figure(&mut self) -> Option<FigureType> {
+        if self.bag.is_empty() {
+            self.refill_bag();
+        }
+        if self.bag.is_empty() {
+            return None;
+        }
+        return Some(self.bag.remove(0));
+    }
+}
+
+// Deals a fixed list of figures in order.
+#[derive(Debug, Clone)]
+pub struct QueueGenerator {
+    figures: VecDeque<FigureType>,
+}
+
+impl QueueGenerator {
+    pub fn new(figures: Vec<FigureType>) -> QueueGenerator {
+        return QueueGenerator {
+            figures: VecDeque::from(figures),
+        };
+    }
+}
+
+impl PieceGenerator for QueueGenerator {
+    fn next_figure(&mut self) -> Option<FigureType> {
+        return self.figures.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod piece_generator_tests {
+    use super::*;
+
+    struct Random {
+        number: i32,
+    }
+
+    impl Randomizer for Random {
+        fn random_between(&self, _first: i32, _last: i32) -> i32 {
+            return self.number;
+        }
+    }
+
+    #[test]
+    fn test_random_generator_uses_randomizer() {
+        let mut generator = RandomGenerator::new(Rc::new(Random { number: 3 }));
+        assert_eq!(generator.next_figure(), Some(FigureType::O));
+    }
+    #[test]
+    fn test_queue_generator_runs_out() {
+        let mut generator = QueueGenerator::new(vec![FigureType::S, FigureType::Z]);
+        assert_eq!(generator.next_figure(), Some(FigureType::S));
+        assert_eq!(generator.next_figure(), Some(FigureType::Z));
+        assert_eq!(generator.next_figure(), None);
+    }
+    #[test]
+    fn test_seven_bag_deals_every_figure() {
+        let mut generator = SevenBagGenerator::new(3);
+        let mut figures: Vec<FigureType> =
+            (0..7).map(|_| generator.next_figure().unwrap()).collect();
+        for figure_type in PieceSet::tetrominoes().get_figures() {
+            let position = figures.iter().position(|figure| figure == figure_type);
+            figures.remove(position.unwrap());
+        }
+        assert!(figures.is_empty());
+    }
+    #[test]
+    fn test_bag_deals_piece_set() {
+        let piece_set = PieceSet::pentominoes();
+        let mut generator = SevenBagGenerator::with_piece_set(5, piece_set.clone());
+        let figures: Vec<FigureType> = (0..18).map(|_| generator.next_figure().unwrap()).collect();
+        for figure_type in piece_set.get_figures() {
+            assert!(figures.contains(figure_type));
+        }
+    }
+    #[test]
+    fn test_random_generator_uses_piece_set() {
+        let piece_set = PieceSet::trominoes();
+        let mut generator =
+            RandomGenerator::with_piece_set(Rc::new(Random { number: 1 }), piece_set.clone());
+        assert_eq!(
+            generator.next_figure(),
+            Some(piece_set.get_figures()[1].clone())
+        );
+    }
+    #[test]
+    fn test_empty_piece_set_runs_out() {
+        let mut generator = SevenBagGenerator::with_piece_set(1, PieceSet::new(vec![]));
+        assert_eq!(generator.next_figure(), None);
+    }
+    #[test]
+    fn test_cloned_bags_deal_the_same_figures() {
+        let mut generator = SevenBagGenerator::new(11);
+        let mut clone = generator.clone();
+        for _ in 0..30 {
+            assert_eq!(generator.next_figure(), clone.next_figure());
+        }
+    }
+    #[test]
+    fn test_different_seeds_deal_different_figures() {
+        let mut first = SevenBagGenerator::new(1);
+        let mut second = SevenBagGenerator::new(2);
+        let first_figures: Vec<FigureType> =
+            (0..14).map(|_| first.next_figure().unwrap()).collect();
+        let second_figures: Vec<FigureType> =
+            (0..14).map(|_| second.next_figure().unwrap()).collect();
+        assert_ne!(first_figures, second_figures);
+    }
+}
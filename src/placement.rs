@@ -0,0 +1,281 @@
+use super::move_validator::{can_move_down, has_valid_position};
+use super::{Action, ActiveFigure, Board, FigureType, Point};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// Position and rotation step.
+type StateKey = (i32, i32, usize);
+
+// A position where a figure can lock: it can't move down any further.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    figure: ActiveFigure,
+    cells: Vec<Point>,
+}
+
+impl Placement {
+    pub(crate) fn new(figure: ActiveFigure, board: &Board) -> Placement {
+        let mut cells: Vec<Point> = figure
+            .to_cartesian()
+            .into_iter()
+            .map(|point| board.wrapped_point(point))
+            .collect();
+        cells.sort_by_key(|point| (point.y, point.x));
+        return Placement { figure, cells };
+    }
+
+    pub fn figure(&self) -> &ActiveFigure {
+        return &self.figure;
+    }
+
+    pub fn position(&self) -> Point {
+        return self.figure.position();
+    }
+
+    pub fn rotation_step(&self) -> usize {
+        return self.figure.rotation_step();
+    }
+
+    // Board cells the figure takes, sorted by row and column.
+    pub fn cells(&self) -> &[Point] {
+        return &self.cells;
+    }
+
+    // The board with the figure locked, without removing completed lines.
+    // Cells above the board are left out.
+    pub(crate) fn locked_on(&self, board: &Board) -> Board {
+        let figure_type = self.figure.get_type();
+        let mut locked = board.clone();
+        for cell in self.cells.iter().filter(|cell| cell.y >= 0) {
+            let (x, y) = (cell.x as usize, cell.y as usize);
+            // Only the occupancy matters here, so garbage (always in the
+            // palette) stands in for a type the board can't hold.
+            if locked.set(x, y, Some(figure_type.clone())).is_err() {
+                let _ = locked.set(x, y, Some(FigureType::Garbage));
+            }
+        }
+        return locked;
+    }
+}
+
+// Every distinct lock placement reachable from `figure` moving, rotating
+// (with wall kicks) and soft dropping, ignoring the gravity timing. This
+// includes tucks and spins that a straight drop can't reach.
+// Placements taking the same cells are returned once, with the figure
+// reached with the fewest actions.
+pub fn reachable_placements(board: &Board, figure: &ActiveFigure) -> Vec<Placement> {
+    let mut placements = vec![];
+    let mut placed_cells = HashSet::new();
+    for state in explore(board, figure) {
+        if can_move_down(&state, board) {
+            continue;
+        }
+        let placement = Placement::new(state, board);
+        let cells: Vec<(i32, i32)> = placement
+            .cells
+            .iter()
+            .map(|point| (point.x, point.y))
+            .collect();
+        if placed_cells.insert(cells) {
+            placements.push(placement);
+        }
+    }
+    return placements;
+}
+
+// Shortest list of actions taking `figure` to the `target` placement,
+// finishing with a hard drop. Any placement taking the same cells is fine.
+pub fn path_to(board: &Board, figure: &ActiveFigure, target: &Placement) -> Option<Vec<Action>> {
+    if !has_valid_position(figure, board) {
+        return None;
+    }
+    let mut parents: HashMap<StateKey, (StateKey, Action)> = HashMap::new();
+    let mut pending = VecDeque::new();
+    let start = state_key(figure);
+    parents.insert(start, (start, Action::HardDrop));
+    pending.push_back(figure.clone());
+    while let Some(state) = pending.pop_front() {
+        if Placement::new(dropped(board, &state), board).cells == target.cells {
+            let mut actions = vec![Action::HardDrop];
+            let mut key = state_key(&state);
+            while key != start {
+                let (parent, action) = parents[&key];
+                actions.push(action);
+                key = parent;
+            }
+            actions.reverse();
+            return Some(actions);
+        }
+        for (action, next) in successors(board, &state) {
+            if let Entry::Vacant(entry) = parents.entry(state_key(&next)) {
+                entry.insert((state_key(&state), action));
+                pending.push_back(next);
+            }
+        }
+    }
+    return None;
+}
+
+pub(crate) fn dropped(board: &Board, figure: &ActiveFigure) -> ActiveFigure {
+    let mut figure = figure.clone();
+    while can_move_down(&figure, board) {
+        figure = figure.moved_down();
+    }
+    return figure;
+}
+
+// Breadth first search over the figure states, in the order they are reached.
+fn explore(board: &Board, figure: &ActiveFigure) -> Vec<ActiveFigure> {
+    let mut states = vec![];
+    if !has_valid_position(figure, board) {
+        return states;
+    }
+    let mut visited = HashSet::new();
+    let mut pending = VecDeque::new();
+    visited.insert(state_key(figure));
+    pending.push_back(figure.clone());
+    while let Some(state) = pending.pop_front() {
+        for (_, next) in successors(board, &state) {
+            if visited.insert(state_key(&next)) {
+                pending.push_back(next);
+            }
+        }
+        states.push(state);
+    }
+    return states;
+}
+
+// Same movement rules as `Game::perform`.
+pub(crate) fn successors(board: &Board, figure: &ActiveFigure) -> Vec<(Action, ActiveFigure)> {
+    let moves = vec![
+        (Action::MoveLeft, Some(figure.moved_left())),
+        (Action::MoveRight, Some(figure.moved_right())),
+        (Action::MoveDown, Some(figure.moved_down())),
+        (
+            Action::Rotate,
+            figure
+                .wall_kicked_rotation_tests()
+                .find(|rotated| has_valid_position(rotated, board)),
+        ),
+    ];
+    return moves
+        .into_iter()
+        .filter_map(|(action, next)| next.map(|next| (action, next)))
+        .filter(|(_, next)| has_valid_position(next, board))
+        .map(|(action, next)| (action, wrapped(board, next)))
+        .collect();
+}
+
+fn state_key(figure: &ActiveFigure) -> StateKey {
+    let position = figure.position();
+    return (position.x, position.y, figure.rotation_step());
+}
+
+fn wrapped(board: &Board, figure: ActiveFigure) -> ActiveFigure {
+    if board.is_wrapping_around() {
+        return figure.wrapped_around(board.width() as i32);
+    }
+    return figure;
+}
+
+#[cfg(test)]
+mod placement_tests {
+    use super::super::{FigureType, Size};
+    use super::*;
+
+    fn board(height: usize, width: usize) -> Board {
+        return Board::new(&Size { height, width });
+    }
+
+    #[test]
+    fn test_o_placements_on_empty_board() {
+        let figure = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        let placements = reachable_placements(&board(6, 4), &figure);
+        assert_eq!(placements.len(), 3);
+        assert!(placements
+            .iter()
+            .all(|placement| placement.cells().iter().all(|cell| cell.y >= 4)));
+    }
+    #[test]
+    fn test_t_placements_on_empty_board() {
+        let figure = ActiveFigure::new(FigureType::T, Point { x: 3, y: 0 });
+        let placements = reachable_placements(&board(20, 10), &figure);
+        // 8 flat placements for each flat orientation and 9 for each upright one.
+        assert_eq!(placements.len(), 34);
+    }
+    #[test]
+    fn test_s_placements_are_distinct() {
+        let figure = ActiveFigure::new(FigureType::S, Point { x: 3, y: 0 });
+        let placements = reachable_placements(&board(20, 10), &figure);
+        assert_eq!(placements.len(), 17);
+    }
+    #[test]
+    fn test_tuck_under_overhang() {
+        // Overhang on row 3 from column 2, open underneath.
+        let mut board = board(6, 6);
+        for x in 2..6 {
+            board = board.replacing_figure_at_xy(x, 3, Some(FigureType::I));
+        }
+        let figure = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        let placements = reachable_placements(&board, &figure);
+        let tucked = placements
+            .iter()
+            .any(|placement| placement.cells().contains(&Point { x: 2, y: 5 }));
+        assert!(tucked);
+    }
+    #[test]
+    fn test_path_to_straight_drop() {
+        let board = board(6, 6);
+        let figure = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        let target = reachable_placements(&board, &figure)
+            .into_iter()
+            .find(|placement| placement.position().x == 2)
+            .unwrap();
+        let path = path_to(&board, &figure, &target).unwrap();
+        assert_eq!(
+            path,
+            vec![Action::MoveRight, Action::MoveRight, Action::HardDrop]
+        );
+    }
+    #[test]
+    fn test_path_to_tuck() {
+        let mut board = board(6, 6);
+        for x in 2..6 {
+            board = board.replacing_figure_at_xy(x, 3, Some(FigureType::I));
+        }
+        let figure = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        let target = reachable_placements(&board, &figure)
+            .into_iter()
+            .find(|placement| placement.cells().contains(&Point { x: 3, y: 5 }))
+            .unwrap();
+        let path = path_to(&board, &figure, &target).unwrap();
+        assert_eq!(
+            path,
+            vec![
+                Action::MoveDown,
+                Action::MoveDown,
+                Action::MoveDown,
+                Action::MoveDown,
+                Action::MoveRight,
+                Action::MoveRight,
+                Action::HardDrop
+            ]
+        );
+    }
+    #[test]
+    fn test_path_to_unreachable_placement() {
+        let figure = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        let walled = (0..6).fold(board(6, 4), |walled, y| {
+            walled.replacing_figure_at_xy(2, y, Some(FigureType::I))
+        });
+        let target = reachable_placements(&board(6, 4), &figure).pop().unwrap();
+        assert_eq!(target.position().x, 2);
+        assert_eq!(path_to(&walled, &figure, &target), None);
+    }
+    #[test]
+    fn test_invalid_start_has_no_placements() {
+        let board = board(4, 4).replacing_figure_at_xy(1, 1, Some(FigureType::I));
+        let figure = ActiveFigure::new(FigureType::O, Point { x: 0, y: 0 });
+        assert!(reachable_placements(&board, &figure).is_empty());
+    }
+}
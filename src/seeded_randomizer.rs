@@ -0,0 +1,70 @@
+use super::Randomizer;
+use std::cell::Cell;
+
+// SplitMix64, small and good enough to get reproducible games from a seed.
+#[derive(Debug, Clone)]
+pub struct SeededRandomizer {
+    state: Cell<u64>,
+}
+
+impl SeededRandomizer {
+    pub fn new(seed: u64) -> SeededRandomizer {
+        return SeededRandomizer {
+            state: Cell::new(seed),
+        };
+    }
+
+    fn next_u64(&self) -> u64 {
+        let state = self.state.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+        self.state.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        return z ^ (z >> 31);
+    }
+}
+
+impl Randomizer for SeededRandomizer {
+    // Both ends are included.
+    fn random_between(&self, first: i32, last: i32) -> i32 {
+        if last <= first {
+            return first;
+        }
+        let range = (last as i64 - first as i64 + 1) as u64;
+        return (first as i64 + (self.next_u64() % range) as i64) as i32;
+    }
+}
+
+#[cfg(test)]
+mod seeded_randomizer_tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_numbers() {
+        let first = SeededRandomizer::new(42);
+        let second = SeededRandomizer::new(42);
+        for _ in 0..100 {
+            assert_eq!(first.random_between(0, 100), second.random_between(0, 100));
+        }
+    }
+    #[test]
+    fn test_numbers_are_in_range() {
+        let randomizer = SeededRandomizer::new(7);
+        let numbers: Vec<i32> = (0..1000)
+            .map(|_| randomizer.random_between(-2, 3))
+            .collect();
+        assert!(numbers.iter().all(|number| *number >= -2 && *number <= 3));
+        assert!(numbers.contains(&-2));
+        assert!(numbers.contains(&3));
+    }
+    #[test]
+    fn test_clone_continues_the_sequence() {
+        let randomizer = SeededRandomizer::new(1);
+        randomizer.random_between(0, 10);
+        let clone = randomizer.clone();
+        assert_eq!(
+            randomizer.random_between(0, 1000),
+            clone.random_between(0, 1000)
+        );
+    }
+}
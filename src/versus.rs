@@ -0,0 +1,361 @@
+use super::{Action, AttackTable, Game, GameMode, PieceGenerator, Randomizer, Size};
+use std::cmp::Ordering;
+
+const GARBAGE_DELAY: f64 = 0.5; //secs
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Standing {
+    pub player: usize,
+    pub lines: u32,
+    pub lines_remaining: Option<u32>,
+    pub pieces_per_second: f64,
+    pub time: f64,
+    pub completed: bool,
+    pub game_over: bool,
+}
+
+pub struct Match {
+    players: Vec<Game>,
+    attack_table: AttackTable,
+    garbage_delay: f64,
+}
+
+impl Match {
+    pub fn new(players: Vec<Game>) -> Match {
+        return Match::with_attack_table(players, AttackTable::default(), GARBAGE_DELAY);
+    }
+
+    pub fn with_attack_table(
+        players: Vec<Game>,
+        attack_table: AttackTable,
+        garbage_delay: f64,
+    ) -> Match {
+        return Match {
+            players,
+            attack_table,
+            garbage_delay,
+        };
+    }
+
+    // Every player gets a clone of the same generator, so all of them are dealt the
+    // same figures. Each randomizer is only used for that player's garbage holes.
+    pub fn race<G>(
+        size: &Size,
+        mode: GameMode,
+        generator: G,
+        randomizers: Vec<Box<dyn Randomizer + 'static>>,
+    ) -> Match
+    where
+        G: PieceGenerator + Clone + 'static,
+    {
+        let players = randomizers
+            .into_iter()
+            .map(|randomizer| {
+                let generator = Box::new(generator.clone());
+                return Game::with_generator(size, randomizer, mode.clone(), generator);
+            })
+            .collect();
+        return Match::new(players);
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        for index in 0..self.players.len() {
+            self.players[index].update(delta_time);
+            self.route_garbage(index);
+        }
+    }
+
+    pub fn perform(&mut self, player: usize, action: Action) {
+        if let Some(game) = self.players.get_mut(player) {
+            game.perform(action);
+            self.route_garbage(player);
+        }
+    }
+
+    pub fn player(&self, index: usize) -> Option<&Game> {
+        return self.players.get(index);
+    }
+
+    pub fn players(&self) -> &[Game] {
+        return &self.players;
+    }
+
+    // Only topped out players are eliminated, completing the goal isn't.
+    pub fn alive_players(&self) -> Vec<usize> {
+        return (0..self.players.len())
+            .filter(|index| !self.players[*index].is_game_over())
+            .collect();
+    }
+
+    // A race ends when a player completes the goal, any match when at most
+    // one player is left.
+    pub fn is_finished(&self) -> bool {
+        if self.has_completed_player() {
+            return true;
+        }
+        let alive_players = self.alive_players().len();
+        return alive_players == 0 || (self.players.len() > 1 && alive_players == 1);
+    }
+
+    // The fastest player to complete the goal, or else the last one alive.
+    pub fn winner(&self) -> Option<usize> {
+        if !self.is_finished() {
+            return None;
+        }
+        if self.has_completed_player() {
+            return self.standings().first().map(|standing| standing.player);
+        }
+        return self.alive_players().first().cloned();
+    }
+
+    fn has_completed_player(&self) -> bool {
+        return self
+            .players
+            .iter()
+            .any(|game| game.is_finished() && !game.is_game_over());
+    }
+
+    // Completed players first (fastest time), then players still racing and
+    // topped out players, both by the most lines cleared.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(player, game)| Standing {
+                player,
+                lines: game.get_lines(),
+                lines_remaining: game.get_lines_remaining(),
+                pieces_per_second: game.get_pieces_per_second(),
+                time: game.get_elapsed_time(),
+                completed: game.is_finished() && !game.is_game_over(),
+                game_over: game.is_game_over(),
+            })
+            .collect();
+        standings.sort_by(Match::compare_standings);
+        return standings;
+    }
+
+    fn compare_standings(first: &Standing, second: &Standing) -> Ordering {
+        let first_group = Match::standing_group(first);
+        let second_group = Match::standing_group(second);
+        if first_group != second_group {
+            return first_group.cmp(&second_group);
+        }
+        if first.completed {
+            return first
+                .time
+                .partial_cmp(&second.time)
+                .unwrap_or(Ordering::Equal);
+        }
+        return second.lines.cmp(&first.lines);
+    }
+
+    fn standing_group(standing: &Standing) -> u8 {
+        if standing.completed {
+            return 0;
+        }
+        if standing.game_over {
+            return 2;
+        }
+        return 1;
+    }
+
+    // Garbage routing
+
+    fn route_garbage(&mut self, sender: usize) {
+        let line_clear = match self.players[sender].take_line_clear() {
+            Some(line_clear) => line_clear,
+            None => return,
+        };
+        let attack = self.attack_table.attack_for(&line_clear);
+        let lines = self.players[sender].counter_garbage(attack);
+        if lines == 0 {
+            return;
+        }
+        for (index, player) in self.players.iter_mut().enumerate() {
+            if index != sender && !player.is_finished() {
+                player.receive_garbage(lines, self.garbage_delay);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod versus_tests {
+    use super::super::{SeededRandomizer, SevenBagGenerator};
+    use super::*;
+
+    struct Random {
+        number: i32,
+    }
+
+    impl Randomizer for Random {
+        fn random_between(&self, _first: i32, _last: i32) -> i32 {
+            return self.number;
+        }
+    }
+
+    #[test]
+    fn test_line_clear_sends_garbage() {
+        let mut versus = get_match(vec![i_pieces_game(), t_pieces_game(8)]);
+        update_loops(&mut versus, 3);
+
+        assert_eq!(versus.player(0).unwrap().get_lines(), 1);
+        assert_eq!(versus.player(1).unwrap().get_incoming_garbage(), 1);
+    }
+    #[test]
+    fn test_line_clear_counters_incoming_garbage() {
+        let mut attacker = i_pieces_game();
+        attacker.receive_garbage(3, 10.0);
+        let mut versus = get_match(vec![attacker, t_pieces_game(8)]);
+        update_loops(&mut versus, 3);
+
+        assert_eq!(versus.player(0).unwrap().get_incoming_garbage(), 2);
+        assert_eq!(versus.player(1).unwrap().get_incoming_garbage(), 0);
+    }
+    #[test]
+    fn test_default_table_perfect_clear_attack() {
+        let mut versus = Match::new(vec![i_pieces_game(), t_pieces_game(8)]);
+        update_loops(&mut versus, 3);
+
+        assert_eq!(versus.player(1).unwrap().get_incoming_garbage(), 10);
+    }
+    #[test]
+    fn test_winner_is_last_player_alive() {
+        let mut versus = get_match(vec![t_pieces_game(8), t_pieces_game(2)]);
+        assert_eq!(versus.winner(), None);
+        update_loops(&mut versus, 1);
+
+        assert!(versus.player(1).unwrap().is_game_over());
+        assert!(versus.is_finished());
+        assert_eq!(versus.winner(), Some(0));
+        assert_eq!(versus.alive_players(), vec![0]);
+    }
+
+    #[test]
+    fn test_winner_is_first_player_to_complete_the_goal() {
+        let mode = GameMode::Sprint { line_goal: 1 };
+        let sprinter = Game::with_mode(
+            &Size {
+                height: 4,
+                width: 4,
+            },
+            Box::new(Random { number: 0 }),
+            mode.clone(),
+        );
+        let size = Size {
+            height: 8,
+            width: 10,
+        };
+        let racer = Game::with_mode(&size, Box::new(Random { number: 5 }), mode);
+        let mut versus = get_match(vec![sprinter, racer]);
+        update_loops(&mut versus, 3);
+
+        assert!(versus.player(0).unwrap().is_finished());
+        assert!(!versus.player(1).unwrap().is_finished());
+        assert_eq!(versus.alive_players(), vec![0, 1]);
+        assert!(versus.is_finished());
+        assert_eq!(versus.winner(), Some(0));
+    }
+
+    #[test]
+    fn test_race_players_get_the_same_figures() {
+        let mut race = get_race(GameMode::sprint());
+        for _ in 0..200 {
+            race.update(10.0);
+            let pieces: Vec<u32> = race
+                .players()
+                .iter()
+                .map(|player| player.get_pieces())
+                .collect();
+            assert!(pieces.iter().all(|count| *count == pieces[0]));
+        }
+        assert!(race.player(0).unwrap().get_pieces() > 0);
+        let first = race.player(0).unwrap().draw();
+        let second = race.player(1).unwrap().draw();
+        assert_eq!(first, second);
+    }
+    #[test]
+    fn test_race_standings() {
+        let mut race = get_race(GameMode::Sprint { line_goal: 40 });
+        race.update(0.1);
+        let standings = race.standings();
+
+        assert_eq!(standings.len(), 3);
+        assert_eq!(standings[0].lines_remaining, Some(40));
+        assert_eq!(standings[0].pieces_per_second, 0.0);
+        assert_eq!(standings[0].time, 0.1);
+        assert!(!standings[0].completed);
+    }
+    #[test]
+    fn test_standings_order() {
+        let completed = standing(0, 40, 30.0, true, false);
+        let slower = standing(1, 40, 35.0, true, false);
+        let racing = standing(2, 20, 35.0, false, false);
+        let topped_out = standing(3, 30, 20.0, false, true);
+        let mut standings = vec![
+            topped_out.clone(),
+            racing.clone(),
+            slower.clone(),
+            completed.clone(),
+        ];
+        standings.sort_by(Match::compare_standings);
+        assert_eq!(standings, vec![completed, slower, racing, topped_out]);
+    }
+
+    // HELPERS
+
+    fn standing(
+        player: usize,
+        lines: u32,
+        time: f64,
+        completed: bool,
+        game_over: bool,
+    ) -> Standing {
+        return Standing {
+            player,
+            lines,
+            lines_remaining: None,
+            pieces_per_second: 0.0,
+            time,
+            completed,
+            game_over,
+        };
+    }
+    fn get_race(mode: GameMode) -> Match {
+        let size = Size {
+            height: 20,
+            width: 10,
+        };
+        let randomizers: Vec<Box<dyn Randomizer>> = (0..3)
+            .map(|seed| Box::new(SeededRandomizer::new(seed)) as Box<dyn Randomizer>)
+            .collect();
+        return Match::race(&size, mode, SevenBagGenerator::new(9), randomizers);
+    }
+
+    fn get_match(players: Vec<Game>) -> Match {
+        let attack_table = AttackTable {
+            single: 1,
+            perfect_clear: 0,
+            ..AttackTable::default()
+        };
+        return Match::with_attack_table(players, attack_table, 0.0);
+    }
+    fn i_pieces_game() -> Game {
+        let size = Size {
+            height: 4,
+            width: 4,
+        };
+        return Game::new(&size, Box::new(Random { number: 0 }));
+    }
+    fn t_pieces_game(height: usize) -> Game {
+        let size = Size { height, width: 10 };
+        return Game::new(&size, Box::new(Random { number: 5 }));
+    }
+    fn update_loops(versus: &mut Match, update_times: u32) {
+        for _ in 0..update_times {
+            versus.update(10.0);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackVisibility {
+    Visible,
+    Invisible,
+    Fading { fade_time: f64 },
+}
+
+// Shows the whole stack for `duration` seconds at the start of every `period`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RevealFlash {
+    pub period: f64,
+    pub duration: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visibility {
+    pub stack: StackVisibility,
+    pub reveal_flash: Option<RevealFlash>,
+}
+
+impl Visibility {
+    pub fn visible() -> Visibility {
+        return Visibility {
+            stack: StackVisibility::Visible,
+            reveal_flash: None,
+        };
+    }
+
+    pub fn invisible() -> Visibility {
+        return Visibility {
+            stack: StackVisibility::Invisible,
+            reveal_flash: None,
+        };
+    }
+
+    pub fn fading(fade_time: f64) -> Visibility {
+        return Visibility {
+            stack: StackVisibility::Fading { fade_time },
+            reveal_flash: None,
+        };
+    }
+
+    // Alpha multiplier for a block locked at `lock_time`, seen at game time `time`.
+    pub fn alpha_for(&self, lock_time: f64, time: f64) -> f32 {
+        if self.is_revealing(time) {
+            return 1.0;
+        }
+        return match self.stack {
+            StackVisibility::Visible => 1.0,
+            StackVisibility::Invisible => 0.0,
+            StackVisibility::Fading { fade_time } => {
+                if fade_time <= 0.0 {
+                    return 0.0;
+                }
+                let faded = (time - lock_time) / fade_time;
+                (1.0 - faded).clamp(0.0, 1.0) as f32
+            }
+        };
+    }
+
+    fn is_revealing(&self, time: f64) -> bool {
+        if let Some(reveal_flash) = &self.reveal_flash {
+            if reveal_flash.period > 0.0 {
+                return time % reveal_flash.period < reveal_flash.duration;
+            }
+        }
+        return false;
+    }
+}
+
+impl Default for Visibility {
+    fn default() -> Visibility {
+        return Visibility::visible();
+    }
+}
+
+#[cfg(test)]
+mod visibility_tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_stack() {
+        assert_eq!(Visibility::visible().alpha_for(0.0, 100.0), 1.0);
+    }
+    #[test]
+    fn test_invisible_stack() {
+        assert_eq!(Visibility::invisible().alpha_for(1.0, 1.0), 0.0);
+    }
+    #[test]
+    fn test_fading_stack() {
+        let visibility = Visibility::fading(2.0);
+        assert_eq!(visibility.alpha_for(1.0, 1.0), 1.0);
+        assert_eq!(visibility.alpha_for(1.0, 2.0), 0.5);
+        assert_eq!(visibility.alpha_for(1.0, 3.0), 0.0);
+        assert_eq!(visibility.alpha_for(1.0, 10.0), 0.0);
+    }
+    #[test]
+    fn test_reveal_flash() {
+        let visibility = Visibility {
+            reveal_flash: Some(RevealFlash {
+                period: 10.0,
+                duration: 1.0,
+            }),
+            ..Visibility::invisible()
+        };
+        assert_eq!(visibility.alpha_for(0.0, 10.5), 1.0);
+        assert_eq!(visibility.alpha_for(0.0, 11.5), 0.0);
+        assert_eq!(visibility.alpha_for(0.0, 20.2), 1.0);
+    }
+}